@@ -1,4 +1,4 @@
-use crate::GameState;
+use crate::{worldgen::WorldGenPreset, GameState};
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 use rand::prelude::*;
@@ -262,11 +262,45 @@ pub mod trees {
         fn build(&self, app: &mut App) {
             app.add_collection_to_loading_state::<_, FallTree>(GameState::Loading)
                 .add_collection_to_loading_state::<_, FruitTree>(GameState::Loading)
-                .add_collection_to_loading_state::<_, MossTree>(GameState::Loading);
+                .add_collection_to_loading_state::<_, MossTree>(GameState::Loading)
+                .add_systems(
+                    Update,
+                    init_tree_collection
+                        .run_if(in_state(GameState::Loading))
+                        .run_if(not(resource_exists::<TreeCollection>())),
+                );
         }
     }
 
-    #[derive(AssetCollection, Resource)]
+    fn init_tree_collection(world: &mut World) {
+        let Some(fall) = world.get_resource::<FallTree>().map(|r| r.to_owned()) else {
+            return;
+        };
+        let Some(fruit) = world.get_resource::<FruitTree>().map(|r| r.to_owned()) else {
+            return;
+        };
+        let Some(moss) = world.get_resource::<MossTree>().map(|r| r.to_owned()) else {
+            return;
+        };
+
+        let tree_collection = TreeCollection {
+            fall_tree: fall,
+            fruit_tree: fruit,
+            moss_tree: moss,
+        };
+
+        world.insert_resource(tree_collection);
+        info!("Tree collection initialized");
+    }
+
+    #[derive(Resource)]
+    pub struct TreeCollection {
+        pub fall_tree: FallTree,
+        pub fruit_tree: FruitTree,
+        pub moss_tree: MossTree,
+    }
+
+    #[derive(AssetCollection, Resource, Clone)]
     pub struct FallTree {
         #[asset(path = "objects/trees/fallTree/large.png")]
         pub large: Handle<Image>,
@@ -276,7 +310,7 @@ pub mod trees {
         pub small: Handle<Image>,
     }
 
-    #[derive(AssetCollection, Resource)]
+    #[derive(AssetCollection, Resource, Clone)]
     pub struct FruitTree {
         #[asset(path = "objects/trees/fruitTree/large.png")]
         pub large: Handle<Image>,
@@ -286,7 +320,7 @@ pub mod trees {
         pub small: Handle<Image>,
     }
 
-    #[derive(AssetCollection, Resource)]
+    #[derive(AssetCollection, Resource, Clone)]
     pub struct MossTree {
         #[asset(path = "objects/trees/mossTree/large.png")]
         pub large: Handle<Image>,
@@ -295,6 +329,48 @@ pub mod trees {
         #[asset(path = "objects/trees/mossTree/small.png")]
         pub small: Handle<Image>,
     }
+
+    pub trait TreeAsset {
+        fn get_large(&self) -> Handle<Image>;
+        fn get_medium(&self) -> Handle<Image>;
+        fn get_small(&self) -> Handle<Image>;
+    }
+
+    impl TreeAsset for FallTree {
+        fn get_large(&self) -> Handle<Image> {
+            self.large.clone()
+        }
+        fn get_medium(&self) -> Handle<Image> {
+            self.medium.clone()
+        }
+        fn get_small(&self) -> Handle<Image> {
+            self.small.clone()
+        }
+    }
+
+    impl TreeAsset for FruitTree {
+        fn get_large(&self) -> Handle<Image> {
+            self.large.clone()
+        }
+        fn get_medium(&self) -> Handle<Image> {
+            self.medium.clone()
+        }
+        fn get_small(&self) -> Handle<Image> {
+            self.small.clone()
+        }
+    }
+
+    impl TreeAsset for MossTree {
+        fn get_large(&self) -> Handle<Image> {
+            self.large.clone()
+        }
+        fn get_medium(&self) -> Handle<Image> {
+            self.medium.clone()
+        }
+        fn get_small(&self) -> Handle<Image> {
+            self.small.clone()
+        }
+    }
 }
 
 #[derive(AssetCollection, Resource)]
@@ -302,26 +378,83 @@ pub struct GroundBase {
     #[asset(texture_atlas(tile_size_x = 200., tile_size_y = 200., columns = 5, rows = 3,))]
     #[asset(path = "dirtSpritesheet.png")]
     pub dirt: Handle<TextureAtlas>,
+    #[asset(path = "water.png")]
+    pub water: Handle<Image>,
 }
 
-#[repr(usize)]
+/// A dirt tile, named after the sides of it which border grass
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
-#[allow(dead_code)]
 pub enum DirtTile {
-    TopLeft = 0,
-    TopMiddle = 1,
-    TopRight = 2,
-    MiddleLeft = 5,
+    TopLeft,
+    TopMiddle,
+    TopRight,
+    MiddleLeft,
     #[default]
-    MiddleMiddle = 6,
-    MiddleRight = 7,
-    BottomLeft = 10,
-    BottomMiddle = 11,
-    BottomRight = 12,
-    OutsideTopLeft = 3,
-    OutsideTopRight = 4,
-    OutsideBottomLeft = 8,
-    OutsideBottomRight = 9,
+    MiddleMiddle,
+    MiddleRight,
+    BottomLeft,
+    BottomMiddle,
+    BottomRight,
+    OutsideTopLeft,
+    OutsideTopRight,
+    OutsideBottomLeft,
+    OutsideBottomRight,
+    TopAndBottom,
+    LeftAndRight,
+    /// Grass on every side except the bottom
+    OpenBottom,
+    /// Grass on every side except the top
+    OpenTop,
+    /// Grass on every side except the right
+    OpenRight,
+    /// Grass on every side except the left
+    OpenLeft,
+    /// Grass on all four sides
+    Surrounded,
+}
+
+impl DirtTile {
+    /// The cell of the dirt spritesheet to draw this tile with. The spritesheet has no art for strips of dirt
+    /// one tile wide, so those variants borrow the closest edge until it does.
+    pub fn atlas_index(self) -> usize {
+        match self {
+            DirtTile::TopLeft => 0,
+            DirtTile::TopMiddle => 1,
+            DirtTile::TopRight => 2,
+            DirtTile::OutsideTopLeft => 3,
+            DirtTile::OutsideTopRight => 4,
+            DirtTile::MiddleLeft => 5,
+            DirtTile::MiddleMiddle => 6,
+            DirtTile::MiddleRight => 7,
+            DirtTile::OutsideBottomLeft => 8,
+            DirtTile::OutsideBottomRight => 9,
+            DirtTile::BottomLeft => 10,
+            DirtTile::BottomMiddle => 11,
+            DirtTile::BottomRight => 12,
+            DirtTile::TopAndBottom => DirtTile::TopMiddle.atlas_index(),
+            DirtTile::LeftAndRight => DirtTile::MiddleLeft.atlas_index(),
+            DirtTile::OpenBottom => DirtTile::TopMiddle.atlas_index(),
+            DirtTile::OpenTop => DirtTile::BottomMiddle.atlas_index(),
+            DirtTile::OpenRight => DirtTile::MiddleLeft.atlas_index(),
+            DirtTile::OpenLeft => DirtTile::MiddleRight.atlas_index(),
+            // the upper edge sits in front on screen, the same way upper corners win in the autotiler
+            DirtTile::Surrounded => DirtTile::TopMiddle.atlas_index(),
+        }
+    }
+}
+
+/// Every world generation preset which can be picked on the new game screen
+#[derive(AssetCollection, Resource)]
+pub struct WorldGenPresets {
+    #[asset(
+        paths(
+            "presets/plains.preset.ron",
+            "presets/rocky.preset.ron",
+            "presets/islands.preset.ron"
+        ),
+        collection(typed)
+    )]
+    pub presets: Vec<Handle<WorldGenPreset>>,
 }
 
 #[derive(AssetCollection, Resource)]
@@ -365,8 +498,8 @@ pub struct MalePawns {
 }
 
 impl MalePawns {
-    pub fn get_random(&self) -> Handle<TextureAtlas> {
-        let random = rand::thread_rng().gen_range(1..12);
+    pub fn get_random(&self, rng: &mut impl Rng) -> Handle<TextureAtlas> {
+        let random = rng.gen_range(1..12);
         match random {
             1 => self.male1.clone(),
             2 => self.male2.clone(),
@@ -400,6 +533,7 @@ impl Plugin for GameAssets {
     fn build(&self, app: &mut App) {
         app.add_plugins((rocks::RockPlugin, trees::TreePlugin))
             .add_collection_to_loading_state::<_, GroundBase>(GameState::Loading)
-            .add_collection_to_loading_state::<_, MalePawns>(GameState::Loading);
+            .add_collection_to_loading_state::<_, MalePawns>(GameState::Loading)
+            .add_collection_to_loading_state::<_, WorldGenPresets>(GameState::Loading);
     }
 }
@@ -0,0 +1,270 @@
+//! Generates a world without opening a window and writes it out as a PNG, and optionally as ASCII, so maps
+//! can be compared across seeds, sizes and presets without launching the game.
+//!
+//! ```text
+//! cargo run --bin map_preview -- --seed 1234 --size 256 --preset assets/presets/rocky.preset.ron \
+//!     --out map.png --scale 2 --ascii map.txt
+//! ```
+
+use bevy::math::IVec2;
+use image::{Rgb, RgbImage};
+use rimguard_realms::{
+    factory::FACTORY_SIZE,
+    grid::Grid,
+    rng::{parse_seed, GameRng},
+    stone::StoneKind,
+    tilemap::TileType,
+    utils::TilePos,
+    worldgen::{classify_stones, classify_tiles, generate_noise, place_trees, WorldGenPreset},
+    MapSettings, WorldNoise,
+};
+use std::{fmt::Write as _, process::ExitCode};
+
+const USAGE: &str = "\
+Usage: map_preview [options]
+
+Options:
+    --seed <seed>      the seed to generate, a number or any text (default: random)
+    --size <tiles>     the width and height of the map (default: 128)
+    --preset <file>    the world generation preset (default: assets/presets/plains.preset.ron)
+    --out <file>       where to write the PNG (default: map_preview.png)
+    --scale <pixels>   the width and height of one tile in the PNG (default: 1)
+    --ascii <file>     also write the map as text, use - for stdout
+
+ASCII legend:
+    ~ water    . dirt    , grass    ^ tree
+    c capped   r red     s salt     o stone    t tan
+    : and ; are dirt and grass where the factory fits";
+
+/// Everything that ends up on one tile of a generated world
+#[derive(Clone, Copy)]
+enum PreviewTile {
+    Ground(TileType),
+    Stone(StoneKind),
+    Tree,
+}
+
+impl PreviewTile {
+    fn color(self) -> [u8; 3] {
+        match self {
+            PreviewTile::Ground(TileType::Water) => [40, 90, 200],
+            PreviewTile::Ground(TileType::Dirt) => [120, 85, 50],
+            PreviewTile::Ground(TileType::Grass) => [70, 150, 60],
+            PreviewTile::Tree => [20, 85, 30],
+            PreviewTile::Stone(StoneKind::Capped) => [90, 90, 110],
+            PreviewTile::Stone(StoneKind::Red) => [170, 60, 50],
+            PreviewTile::Stone(StoneKind::Salt) => [230, 230, 230],
+            PreviewTile::Stone(StoneKind::Stone) => [130, 130, 130],
+            PreviewTile::Stone(StoneKind::Tan) => [200, 170, 120],
+        }
+    }
+
+    fn ascii(self, factory_fits: bool) -> char {
+        match (self, factory_fits) {
+            (PreviewTile::Ground(TileType::Water), _) => '~',
+            (PreviewTile::Ground(TileType::Dirt), false) => '.',
+            (PreviewTile::Ground(TileType::Dirt), true) => ':',
+            (PreviewTile::Ground(TileType::Grass), false) => ',',
+            (PreviewTile::Ground(TileType::Grass), true) => ';',
+            (PreviewTile::Tree, _) => '^',
+            (PreviewTile::Stone(StoneKind::Capped), _) => 'c',
+            (PreviewTile::Stone(StoneKind::Red), _) => 'r',
+            (PreviewTile::Stone(StoneKind::Salt), _) => 's',
+            (PreviewTile::Stone(StoneKind::Stone), _) => 'o',
+            (PreviewTile::Stone(StoneKind::Tan), _) => 't',
+        }
+    }
+
+    fn walkable(self) -> bool {
+        matches!(
+            self,
+            PreviewTile::Ground(TileType::Dirt) | PreviewTile::Ground(TileType::Grass)
+        )
+    }
+}
+
+struct Options {
+    seed: u64,
+    size: usize,
+    preset: String,
+    out: String,
+    scale: u32,
+    ascii: Option<String>,
+}
+
+/// The options on the command line, or `None` when only the usage was asked for
+fn parse_options() -> Result<Option<Options>, String> {
+    let mut options = Options {
+        seed: rand::random(),
+        size: MapSettings::default().size,
+        preset: "assets/presets/plains.preset.ron".to_string(),
+        out: "map_preview.png".to_string(),
+        scale: 1,
+        ascii: None,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            return Ok(None);
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{arg} needs a value\n\n{USAGE}"))?;
+
+        match arg.as_str() {
+            "--seed" => options.seed = parse_seed(&value).ok_or("the seed can't be empty")?,
+            "--size" => {
+                options.size = value
+                    .parse()
+                    .ok()
+                    .filter(|size| *size > 0)
+                    .ok_or_else(|| format!("{value} isn't a valid map size"))?;
+            }
+            "--preset" => options.preset = value,
+            "--out" => options.out = value,
+            "--scale" => {
+                options.scale = value
+                    .parse()
+                    .ok()
+                    .filter(|scale| *scale > 0)
+                    .ok_or_else(|| format!("{value} isn't a valid scale"))?;
+            }
+            "--ascii" => options.ascii = Some(value),
+            _ => return Err(format!("unknown option {arg}\n\n{USAGE}")),
+        }
+    }
+
+    Ok(Some(options))
+}
+
+/// Generate the world the same way the game does when it enters `GameState::WorldSpawn`
+fn generate(seed: u64, size: usize, preset: &WorldGenPreset) -> Grid<PreviewTile> {
+    let mut game_rng = GameRng::from_seed(seed);
+    let mut world_noise = WorldNoise::with_size(size, &mut game_rng.terrain);
+    generate_noise(&mut world_noise, preset);
+
+    let tiles = classify_tiles(&world_noise.base_world, preset);
+    let stones = classify_stones(&world_noise, &tiles, preset, &mut game_rng.stones);
+    let trees = place_trees(
+        &world_noise,
+        &tiles,
+        |pos| stones.get(pos).copied().flatten().is_some(),
+        preset,
+        &mut game_rng.trees,
+    );
+
+    Grid::from_fn(size, size, |pos| {
+        if let Some(Some(deposit)) = stones.get(pos) {
+            PreviewTile::Stone(deposit.kind)
+        } else if let Some(Some(_)) = trees.get(pos) {
+            PreviewTile::Tree
+        } else {
+            PreviewTile::Ground(tiles.get(pos).copied().unwrap_or_default())
+        }
+    })
+}
+
+/// Every tile covered by at least one spot where the factory could be placed
+fn factory_areas(world: &Grid<PreviewTile>) -> Grid<bool> {
+    let footprint = FACTORY_SIZE as i32 - 1;
+    let fits = |pos: TilePos| {
+        world.contains(pos + IVec2::splat(footprint))
+            && world
+                .rect(pos, pos + IVec2::splat(footprint))
+                .all(|pos| world.get(pos).is_some_and(|tile| tile.walkable()))
+    };
+
+    let mut areas = Grid::filled(world.width(), world.height(), false);
+    for pos in world.positions().filter(|pos| fits(*pos)) {
+        for covered in world.rect(pos, pos + IVec2::splat(footprint)) {
+            if let Some(area) = areas.get_mut(covered) {
+                *area = true;
+            }
+        }
+    }
+
+    areas
+}
+
+fn render_png(world: &Grid<PreviewTile>, factory_areas: &Grid<bool>, scale: u32) -> RgbImage {
+    let height = world.height() as u32;
+
+    RgbImage::from_fn(world.width() as u32 * scale, height * scale, |x, y| {
+        // images start at the top, the world starts at the bottom
+        let pos = TilePos::new((x / scale) as i32, (height - 1 - y / scale) as i32);
+        let Some(tile) = world.get(pos) else {
+            return Rgb([0, 0, 0]);
+        };
+
+        let mut color = tile.color();
+        if factory_areas.get(pos) == Some(&true) {
+            // tint the ground the factory fits on towards yellow
+            for (channel, highlight) in color.iter_mut().zip([255u8, 220, 0]) {
+                *channel = ((*channel as u16 * 2 + highlight as u16) / 3) as u8;
+            }
+        }
+
+        Rgb(color)
+    })
+}
+
+fn render_ascii(world: &Grid<PreviewTile>, factory_areas: &Grid<bool>) -> String {
+    let mut ascii = String::with_capacity((world.width() + 1) * world.height());
+
+    for y in (0..world.height() as i32).rev() {
+        for x in 0..world.width() as i32 {
+            let pos = TilePos::new(x, y);
+            if let Some(tile) = world.get(pos) {
+                ascii.push(tile.ascii(factory_areas.get(pos) == Some(&true)));
+            }
+        }
+        let _ = writeln!(ascii);
+    }
+
+    ascii
+}
+
+fn run() -> Result<(), String> {
+    let Some(options) = parse_options()? else {
+        println!("{USAGE}");
+        return Ok(());
+    };
+
+    let preset_source = std::fs::read_to_string(&options.preset)
+        .map_err(|err| format!("could not read {}: {err}", options.preset))?;
+    let preset: WorldGenPreset = ron::from_str(&preset_source)
+        .map_err(|err| format!("could not parse {}: {err}", options.preset))?;
+
+    let world = generate(options.seed, options.size, &preset);
+    let factory_areas = factory_areas(&world);
+
+    render_png(&world, &factory_areas, options.scale)
+        .save(&options.out)
+        .map_err(|err| format!("could not write {}: {err}", options.out))?;
+
+    match options.ascii.as_deref() {
+        Some("-") => print!("{}", render_ascii(&world, &factory_areas)),
+        Some(path) => std::fs::write(path, render_ascii(&world, &factory_areas))
+            .map_err(|err| format!("could not write {path}: {err}"))?,
+        None => {}
+    }
+
+    eprintln!(
+        "Wrote the {} world for seed {} ({}x{}) to {}",
+        preset.name, options.seed, options.size, options.size, options.out
+    );
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
+}
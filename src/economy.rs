@@ -0,0 +1,119 @@
+use crate::stone::StoneKind;
+use bevy::{prelude::*, utils::HashMap};
+use std::fmt;
+
+/// Everything pawns can gather and the colony can spend
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ResourceKind {
+    Stone,
+    TanStone,
+    Salt,
+    RedStone,
+    CappedStone,
+    Wood,
+}
+
+impl ResourceKind {
+    /// Every resource in the order it's listed in the UI
+    pub const ALL: [ResourceKind; 6] = [
+        ResourceKind::Stone,
+        ResourceKind::TanStone,
+        ResourceKind::Salt,
+        ResourceKind::RedStone,
+        ResourceKind::CappedStone,
+        ResourceKind::Wood,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ResourceKind::Stone => "Stone",
+            ResourceKind::TanStone => "Tan Stone",
+            ResourceKind::Salt => "Salt",
+            ResourceKind::RedStone => "Red Stone",
+            ResourceKind::CappedStone => "Capped Stone",
+            ResourceKind::Wood => "Wood",
+        }
+    }
+}
+
+impl From<StoneKind> for ResourceKind {
+    fn from(stone_kind: StoneKind) -> Self {
+        match stone_kind {
+            StoneKind::Stone => ResourceKind::Stone,
+            StoneKind::Tan => ResourceKind::TanStone,
+            StoneKind::Salt => ResourceKind::Salt,
+            StoneKind::Red => ResourceKind::RedStone,
+            StoneKind::Capped => ResourceKind::CappedStone,
+        }
+    }
+}
+
+/// The price of something, paid in one or more kinds of resource
+#[derive(Debug, Clone, Copy)]
+pub struct Bill(pub &'static [(ResourceKind, usize)]);
+
+pub const PAWN_COST: Bill = Bill(&[(ResourceKind::Stone, 75), (ResourceKind::Wood, 25)]);
+
+impl fmt::Display for Bill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (kind, amount)) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{amount} {}", kind.name())?;
+        }
+        Ok(())
+    }
+}
+
+/// An amount of each kind of resource. Used both for the colony's stockpile and for what a pawn is carrying
+#[derive(Debug, Default, Clone)]
+pub struct ResourceStore(HashMap<ResourceKind, usize>);
+
+impl ResourceStore {
+    pub fn get(&self, kind: ResourceKind) -> usize {
+        self.0.get(&kind).copied().unwrap_or_default()
+    }
+
+    pub fn add(&mut self, kind: ResourceKind, amount: usize) {
+        let stored = self.0.entry(kind).or_default();
+        *stored = stored.saturating_add(amount);
+    }
+
+    /// The sum of every kind of resource
+    pub fn total(&self) -> usize {
+        self.0.values().sum()
+    }
+
+    /// Move everything out of `other` and into this store
+    pub fn take_all(&mut self, other: &mut ResourceStore) {
+        for (kind, amount) in other.0.drain() {
+            self.add(kind, amount);
+        }
+    }
+
+    pub fn can_afford(&self, bill: &Bill) -> bool {
+        bill.0
+            .iter()
+            .all(|(kind, amount)| self.get(*kind) >= *amount)
+    }
+
+    /// Pay `bill` if there's enough of every resource on it. Nothing is taken otherwise
+    pub fn try_pay(&mut self, bill: &Bill) -> bool {
+        if !self.can_afford(bill) {
+            return false;
+        }
+
+        for (kind, amount) in bill.0 {
+            if let Some(stored) = self.0.get_mut(kind) {
+                *stored -= amount;
+            }
+        }
+
+        true
+    }
+}
+
+/// The resources the colony has stored in the factory
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct ResourceLedger(pub ResourceStore);
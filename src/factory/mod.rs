@@ -1,11 +1,19 @@
 pub mod components;
 mod systems;
 
-use crate::GameState;
+use crate::{utils::TilePos, GameState};
 use bevy::prelude::*;
 
 pub use components::*;
 
+/// The width and height of the factory in tiles
+pub const FACTORY_SIZE: usize = 4;
+
+/// The bottom left and top right tiles covered by a factory anchored in the bottom left at `pos`
+pub fn factory_bounds(pos: TilePos) -> (TilePos, TilePos) {
+    (pos, pos + IVec2::splat(FACTORY_SIZE as i32 - 1))
+}
+
 pub struct FactoryPlugin;
 
 impl Plugin for FactoryPlugin {
@@ -1,11 +1,10 @@
-use super::components::*;
+use super::{components::*, factory_bounds};
+use crate::fog::{Vision, FACTORY_VISION_RADIUS};
 use crate::utils::*;
 use crate::{navmesh, CursorPosition, GameState, GameTile};
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
 
-const FACTORY_SIZE: usize = 4;
-
 pub fn initial_spawn_factory(
     mut commands: Commands,
     cursor_position: Res<CursorPosition>,
@@ -15,7 +14,7 @@ pub fn initial_spawn_factory(
         SpriteBundle {
             texture: asset_server.load("factory.png"),
             transform: Transform::from_translation(
-                cursor_position.0.unwrap_or(Vec2::ZERO).extend(1.),
+                cursor_position.0.unwrap_or_default().corner().extend(1.),
             ),
             sprite: Sprite {
                 anchor: bevy::sprite::Anchor::BottomLeft,
@@ -42,7 +41,7 @@ pub fn clamp_factory_to_cursor_position(
         return;
     };
 
-    factory_transform.translation = (cursor_position.tile_pos_to_world()).extend(1.);
+    factory_transform.translation = cursor_position.corner().extend(1.);
 }
 
 pub fn place_factory(
@@ -51,6 +50,7 @@ pub fn place_factory(
     input: Query<&ActionState<crate::Input>>,
     mut game_state: ResMut<NextState<GameState>>,
     mut navmesh: ResMut<navmesh::components::Navmesh>,
+    mut flow_field: ResMut<navmesh::components::FlowField>,
 ) {
     let Ok((factory_entity, factory_transform)) = q_factory.get_single() else {
         return;
@@ -61,36 +61,56 @@ pub fn place_factory(
     };
 
     if input.just_pressed(crate::Input::Select) {
-        let Vec2 { x, y } = factory_transform.translation().world_pos_to_tile();
+        let factory_pos = factory_transform.tile_pos();
 
-        if !check_spawn_bounds_by_navtiles(&navmesh, x, y) {
+        if !check_spawn_bounds_by_navtiles(&navmesh, factory_pos) {
             return;
         }
 
-        commands.entity(factory_entity).insert((Placed, GameTile));
+        commands.entity(factory_entity).insert((
+            Placed,
+            GameTile,
+            Vision {
+                radius: FACTORY_VISION_RADIUS,
+            },
+        ));
         commands.entity(factory_entity).remove::<AabbGizmo>();
         game_state.set(GameState::PawnSpawn);
 
         // mark navmesh tiles as occupied
-        for x in x as usize..x as usize + FACTORY_SIZE {
-            for y in y as usize..y as usize + FACTORY_SIZE {
-                navmesh.0[x][y].walkable = false;
-            }
+        let (min, max) = factory_bounds(factory_pos);
+        let footprint = navmesh.tiles().rect(min, max).collect::<Vec<_>>();
+        for &pos in &footprint {
+            navmesh.set_walkable(pos, false);
         }
+
+        // enemies converge on the factory by following a flow field toward the tiles around its footprint. The
+        // field skips the ones which are blocked each time it is rebuilt, so a tree growing there later is fine
+        let goal = navmesh::components::PathGoal::Around { min, max };
+        let perimeter = navmesh
+            .tiles()
+            .rect(min - IVec2::ONE, max + IVec2::ONE)
+            .filter(|&pos| goal.contains(pos))
+            .collect::<Vec<_>>();
+        flow_field.set_goals(perimeter);
     }
 }
 
-fn check_spawn_bounds_by_navtiles(navmesh: &navmesh::components::Navmesh, x: f32, y: f32) -> bool {
-    let mut is_valid = true;
+fn check_spawn_bounds_by_navtiles(navmesh: &navmesh::components::Navmesh, pos: TilePos) -> bool {
+    let (min, max) = factory_bounds(pos);
 
-    // check navmesh bounds for non-walkable tiles assuming the factory is anchored in the bottom left
-    for x in x as usize..x as usize + FACTORY_SIZE {
-        for y in y as usize..y as usize + FACTORY_SIZE {
-            if !navmesh.0[x][y].walkable {
-                is_valid = false;
-            }
-        }
-    }
+    // every tile under the factory must be inside the map, walkable and connected to the others
+    let Some(region) = navmesh.region(min) else {
+        return false;
+    };
+    let footprint_connected = (min.x()..=max.x())
+        .flat_map(|x| (min.y()..=max.y()).map(move |y| TilePos::new(x, y)))
+        .all(|pos| navmesh.region(pos) == Some(region));
 
-    is_valid
+    // and enemies must be able to walk to it from the edge of the map, so it can't be hidden on an island
+    footprint_connected
+        && navmesh
+            .tiles()
+            .edges()
+            .any(|pos| navmesh.region(pos) == Some(region))
 }
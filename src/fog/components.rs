@@ -0,0 +1,52 @@
+use crate::{grid::Grid, utils::TilePos};
+use bevy::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TileVisibility {
+    /// Never seen by anything, drawn fully dark
+    #[default]
+    Unexplored,
+    /// Seen before but not right now, drawn dimmed and hides enemies
+    Explored,
+    /// Inside the vision of a pawn or the factory
+    Visible,
+}
+
+impl TileVisibility {
+    /// The alpha of the fog drawn over a tile
+    pub fn fog_alpha(self) -> u8 {
+        match self {
+            TileVisibility::Unexplored => 255,
+            TileVisibility::Explored => 140,
+            TileVisibility::Visible => 0,
+        }
+    }
+}
+
+/// Reveals the tiles within `radius` tiles of the entity
+#[derive(Component, Debug)]
+pub struct Vision {
+    pub radius: i32,
+}
+
+/// What the colony has seen of the map
+#[derive(Resource, Default)]
+pub struct FogOfWar {
+    pub tiles: Grid<TileVisibility>,
+    /// Every tile which was visible after the last update, so only those need dimming on the next one
+    pub visible: Vec<TilePos>,
+    /// The tile and vision radius of every viewer at the last update, sorted so they can be compared
+    pub viewers: Vec<(TilePos, i32)>,
+    /// The image drawn over the map, one pixel per tile
+    pub overlay: Handle<Image>,
+}
+
+impl FogOfWar {
+    pub fn is_visible(&self, pos: TilePos) -> bool {
+        self.tiles.get(pos) == Some(&TileVisibility::Visible)
+    }
+}
+
+/// The sprite drawing the [`FogOfWar`] overlay
+#[derive(Component)]
+pub struct FogOverlay;
@@ -0,0 +1,29 @@
+mod components;
+mod systems;
+
+use crate::{build_map, GameState};
+use bevy::prelude::*;
+
+pub use components::*;
+
+pub const PAWN_VISION_RADIUS: i32 = 8;
+pub const FACTORY_VISION_RADIUS: i32 = 12;
+
+pub struct FogPlugin;
+
+impl Plugin for FogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FogOfWar>()
+            .add_systems(
+                OnEnter(GameState::WorldSpawn),
+                systems::spawn_fog.after(build_map),
+            )
+            .add_systems(OnEnter(GameState::PawnSpawn), systems::show_fog)
+            .add_systems(
+                Update,
+                (systems::update_visibility, systems::hide_enemies_in_fog)
+                    .chain()
+                    .run_if(in_state(GameState::Main)),
+            );
+    }
+}
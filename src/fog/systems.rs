@@ -0,0 +1,132 @@
+use super::{FogOfWar, FogOverlay, TileVisibility, Vision};
+use crate::{grid::Grid, pawn::components::Enemy, utils::*, MapSettings};
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+/// Draw the fog above everything else in the world
+const FOG_Z: f32 = 10.;
+
+fn set_fog_pixel(image: &mut Image, size: usize, pos: TilePos, visibility: TileVisibility) {
+    // images start at the top, the world starts at the bottom
+    let row = size - 1 - pos.y() as usize;
+    let index = (row * size + pos.x() as usize) * 4;
+
+    if let Some(alpha) = image.data.get_mut(index + 3) {
+        *alpha = visibility.fog_alpha();
+    }
+}
+
+pub fn spawn_fog(
+    mut commands: Commands,
+    mut fog: ResMut<FogOfWar>,
+    mut images: ResMut<Assets<Image>>,
+    map_settings: Res<MapSettings>,
+) {
+    let size = map_settings.size;
+
+    let overlay = images.add(Image::new_fill(
+        Extent3d {
+            width: size as u32,
+            height: size as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, TileVisibility::Unexplored.fog_alpha()],
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+
+    *fog = FogOfWar {
+        tiles: Grid::new(size, size),
+        visible: Vec::new(),
+        viewers: Vec::new(),
+        overlay: overlay.clone(),
+    };
+
+    // hidden while the factory is being placed, so there's something to pick a spot from
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(map_settings.world_size())),
+                anchor: bevy::sprite::Anchor::BottomLeft,
+                ..default()
+            },
+            texture: overlay,
+            transform: Transform::from_xyz(0., 0., FOG_Z),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        FogOverlay,
+        Name::new("FogOfWar"),
+    ));
+}
+
+pub fn show_fog(mut q_overlay: Query<&mut Visibility, With<FogOverlay>>) {
+    for mut visibility in &mut q_overlay {
+        *visibility = Visibility::Inherited;
+    }
+}
+
+pub fn update_visibility(
+    mut fog: ResMut<FogOfWar>,
+    mut images: ResMut<Assets<Image>>,
+    q_vision: Query<(&GlobalTransform, &Vision)>,
+) {
+    let mut viewers = q_vision
+        .iter()
+        .map(|(transform, vision)| (transform.tile_pos(), vision.radius))
+        .collect::<Vec<_>>();
+    viewers.sort_by_key(|&(pos, radius)| (pos.x(), pos.y(), radius));
+
+    // touching the image uploads all of it to the GPU again, so leave it alone until a viewer moves to another tile
+    if viewers == fog.viewers {
+        return;
+    }
+
+    let fog = &mut *fog;
+    let size = fog.tiles.width();
+    let Some(image) = images.get_mut(&fog.overlay) else {
+        return;
+    };
+
+    // everything seen last update fades to explored, then whatever is still in sight is lit up again
+    for pos in fog.visible.drain(..) {
+        if let Some(tile) = fog.tiles.get_mut(pos) {
+            *tile = TileVisibility::Explored;
+            set_fog_pixel(image, size, pos, TileVisibility::Explored);
+        }
+    }
+
+    for &(center, radius) in &viewers {
+        for pos in fog.tiles.radius(center, radius) {
+            if let Some(tile) = fog.tiles.get_mut(pos) {
+                if *tile != TileVisibility::Visible {
+                    *tile = TileVisibility::Visible;
+                    set_fog_pixel(image, size, pos, TileVisibility::Visible);
+                    fog.visible.push(pos);
+                }
+            }
+        }
+    }
+
+    fog.viewers = viewers;
+}
+
+pub fn hide_enemies_in_fog(
+    fog: Res<FogOfWar>,
+    mut q_enemies: Query<(&Transform, &mut Visibility), With<Enemy>>,
+) {
+    for (transform, mut visibility) in &mut q_enemies {
+        let target = if fog.is_visible(transform.tile_pos()) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+
+        // only write on change so the render world doesn't see every enemy as changed every frame
+        if *visibility != target {
+            *visibility = target;
+        }
+    }
+}
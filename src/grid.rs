@@ -0,0 +1,156 @@
+use crate::utils::TilePos;
+use bevy::prelude::*;
+
+/// Offsets to the direct neighbors of a tile in the order up, down, left, right
+pub const NEIGHBORS_4: [IVec2; 4] = [IVec2::Y, IVec2::NEG_Y, IVec2::NEG_X, IVec2::X];
+
+/// Offsets to all of the neighbors of a tile. The direct neighbors come first (in the same order as
+/// [`NEIGHBORS_4`]), followed by the diagonals top left, top right, bottom left, bottom right
+pub const NEIGHBORS_8: [IVec2; 8] = [
+    IVec2::Y,
+    IVec2::NEG_Y,
+    IVec2::NEG_X,
+    IVec2::X,
+    IVec2::new(-1, 1),
+    IVec2::new(1, 1),
+    IVec2::new(-1, -1),
+    IVec2::new(1, -1),
+];
+
+/// A heap allocated 2d grid of tiles addressed by [`TilePos`]. Cells are stored in a single row-major `Vec`,
+/// and every accessor is bounds checked so out of range positions return `None` instead of panicking.
+#[derive(Debug, Clone, Default)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Default> Grid<T> {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::from_fn(width, height, |_| T::default())
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn filled(width: usize, height: usize, value: T) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![value; width * height],
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(TilePos) -> T) -> Self {
+        let mut cells = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                cells.push(f(TilePos::new(x as i32, y as i32)));
+            }
+        }
+
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, pos: TilePos) -> bool {
+        pos.x() >= 0
+            && pos.y() >= 0
+            && (pos.x() as usize) < self.width
+            && (pos.y() as usize) < self.height
+    }
+
+    fn index_of(&self, pos: TilePos) -> Option<usize> {
+        self.contains(pos)
+            .then(|| pos.y() as usize * self.width + pos.x() as usize)
+    }
+
+    pub fn get(&self, pos: TilePos) -> Option<&T> {
+        self.index_of(pos).map(|index| &self.cells[index])
+    }
+
+    pub fn get_mut(&mut self, pos: TilePos) -> Option<&mut T> {
+        self.index_of(pos).map(|index| &mut self.cells[index])
+    }
+
+    /// Every position in the grid in row-major order
+    pub fn positions(&self) -> impl Iterator<Item = TilePos> {
+        let width = self.width;
+        (0..self.width * self.height)
+            .map(move |index| TilePos::new((index % width) as i32, (index / width) as i32))
+    }
+
+    /// Every position along the outer border of the grid
+    pub fn edges(&self) -> impl Iterator<Item = TilePos> {
+        let width = self.width as i32;
+        let height = self.height as i32;
+
+        // the bottom and top rows, then the left and right columns between them, without visiting a tile twice
+        // on grids only one tile wide or high
+        let rows = [0, height - 1]
+            .into_iter()
+            .take(height.min(2) as usize)
+            .flat_map(move |y| (0..width).map(move |x| TilePos::new(x, y)));
+        let columns = [0, width - 1]
+            .into_iter()
+            .take(width.min(2) as usize)
+            .flat_map(move |x| (1..height - 1).map(move |y| TilePos::new(x, y)));
+
+        rows.chain(columns)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TilePos, &T)> {
+        self.positions().zip(self.cells.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (TilePos, &mut T)> {
+        self.positions().zip(self.cells.iter_mut())
+    }
+
+    /// The direct (up, down, left, right) neighbors of `pos` which are inside the grid
+    pub fn neighbors_4(&self, pos: TilePos) -> impl Iterator<Item = TilePos> + '_ {
+        NEIGHBORS_4
+            .into_iter()
+            .map(move |offset| pos + offset)
+            .filter(|neighbor| self.contains(*neighbor))
+    }
+
+    /// The direct and diagonal neighbors of `pos` which are inside the grid
+    pub fn neighbors_8(&self, pos: TilePos) -> impl Iterator<Item = TilePos> + '_ {
+        NEIGHBORS_8
+            .into_iter()
+            .map(move |offset| pos + offset)
+            .filter(|neighbor| self.contains(*neighbor))
+    }
+
+    /// Every position inside the grid within the inclusive rectangle spanned by `min` and `max`
+    pub fn rect(&self, min: TilePos, max: TilePos) -> impl Iterator<Item = TilePos> {
+        let min_x = min.x().max(0);
+        let min_y = min.y().max(0);
+        let max_x = max.x().min(self.width as i32 - 1);
+        let max_y = max.y().min(self.height as i32 - 1);
+
+        (min_y..=max_y).flat_map(move |y| (min_x..=max_x).map(move |x| TilePos::new(x, y)))
+    }
+
+    /// Every position inside the grid whose center is within `radius` tiles of `center`
+    pub fn radius(&self, center: TilePos, radius: i32) -> impl Iterator<Item = TilePos> {
+        let offset = IVec2::splat(radius);
+
+        self.rect(center - offset, center + offset)
+            .filter(move |pos| (pos.0 - center.0).length_squared() <= radius * radius)
+    }
+}
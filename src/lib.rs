@@ -0,0 +1,407 @@
+mod assets;
+pub mod economy;
+pub mod factory;
+mod fog;
+pub mod grid;
+mod navmesh;
+mod pawn;
+pub mod rng;
+pub mod stone;
+pub mod tilemap;
+pub mod tree;
+mod ui;
+pub mod utils;
+pub mod worldgen;
+
+use assets::GameAssets;
+use bevy::{asset::AssetMetaCheck, prelude::*, window::PrimaryWindow};
+use bevy_asset_loader::loading_state::{LoadingState, LoadingStateAppExt};
+use bevy_easings::*;
+use bevy_inspector_egui::quick::FilterQueryInspectorPlugin;
+use grid::Grid;
+use leafwing_input_manager::{axislike::VirtualAxis, prelude::*};
+use rand::prelude::*;
+use rng::GameRng;
+use tilemap::{TileType, Tilemap};
+use utils::{TilePos, WorldPos};
+use worldgen::WorldGenPreset;
+
+const TILE_SIZE: f32 = 16.;
+
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone, Reflect)]
+pub enum GameState {
+    #[default]
+    Loading,
+    NewGame,
+    WorldSpawn,
+    FactoryPlacement,
+    PawnSpawn,
+    Main,
+    Paused,
+}
+
+#[derive(Actionlike, Reflect, Clone, Hash, PartialEq, Eq, Debug)]
+pub enum Input {
+    Pan,
+    Zoom,
+    Select,
+    Debug,
+    DebugSpawnPawn,
+    Pause,
+}
+
+/// Build the app and run the game until the window is closed
+pub fn run() {
+    App::new()
+        .add_state::<GameState>()
+        .add_loading_state(
+            LoadingState::new(GameState::Loading).continue_to_state(GameState::NewGame),
+        )
+        .insert_resource(AssetMetaCheck::Never)
+        .add_plugins((
+            DefaultPlugins
+                .build()
+                .set(ImagePlugin::default_nearest())
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        canvas: Some("#canvas".into()),
+                        fit_canvas_to_parent: true,
+                        ..default()
+                    }),
+                    ..default()
+                }),
+            worldgen::WorldGenPlugin,
+            GameAssets,
+            #[cfg(debug_assertions)]
+            FilterQueryInspectorPlugin::<With<pawn::components::Pawn>>::default(),
+        ))
+        .add_plugins(InputManagerPlugin::<Input>::default())
+        .add_plugins((
+            pawn::PawnPlugin,
+            tilemap::TilemapPlugin,
+            stone::StonePlugin,
+            tree::TreePlugin,
+            fog::FogPlugin,
+            factory::FactoryPlugin,
+            ui::UIPlugin,
+            navmesh::NavmeshPlugin,
+        ))
+        .add_systems(OnEnter(GameState::WorldSpawn), build_map)
+        .add_systems(
+            Update,
+            (
+                update_cursor_position,
+                toggle_paused
+                    .run_if(in_state(GameState::Main).or_else(in_state(GameState::Paused))),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                camera_interactions.run_if(in_state(GameState::Main).or_else(
+                    in_state(GameState::FactoryPlacement).or_else(in_state(GameState::Paused)),
+                )),
+                selection_gizmo.after(camera_interactions),
+            ),
+        )
+        .init_resource::<MapSettings>()
+        .init_resource::<GameRng>()
+        .init_resource::<WorldNoise>()
+        .init_resource::<CursorPosition>()
+        .init_resource::<GameResources>()
+        .init_resource::<economy::ResourceLedger>()
+        .run();
+}
+
+#[derive(Component)]
+pub struct CameraMetadata {
+    pub target: Vec3,
+    pub zoom: f32,
+    /// The world position of the mouse when the user started clicking and where the user is dragging to. None if the user is not dragging.
+    pub selection_world_bounds: Option<(Vec2, Vec2)>,
+}
+
+/// The dimensions of the map, chosen on the new game screen before the world is spawned
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapSettings {
+    /// The width and height of the map in tiles
+    pub size: usize,
+}
+
+impl MapSettings {
+    pub const SIZES: [usize; 3] = [128, 256, 512];
+
+    /// The width and height of the map in world units
+    pub fn world_size(&self) -> f32 {
+        self.size as f32 * TILE_SIZE
+    }
+}
+
+impl Default for MapSettings {
+    fn default() -> Self {
+        Self {
+            size: Self::SIZES[0],
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct WorldNoise {
+    pub base_world: Grid<f32>,
+    pub base_resources: Grid<f32>,
+    pub seed: f32,
+    pub offset: u16,
+}
+
+impl WorldNoise {
+    pub fn with_size(size: usize, rng: &mut impl Rng) -> Self {
+        Self {
+            base_world: Grid::new(size, size),
+            base_resources: Grid::new(size, size),
+            seed: rng.gen::<f32>(),
+            offset: rng.gen::<u16>(),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.base_world.width()
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct GameResources {
+    pub pawns: usize,
+}
+
+/// The tile currently under the mouse cursor
+#[derive(Resource, Default)]
+pub struct CursorPosition(pub Option<TilePos>);
+
+#[derive(Component)]
+struct GameTile;
+
+pub fn build_map(
+    mut commands: Commands,
+    mut world_noise: ResMut<WorldNoise>,
+    mut navmesh: ResMut<navmesh::components::Navmesh>,
+    map_settings: Res<MapSettings>,
+    mut game_rng: ResMut<GameRng>,
+    mut tilemap: ResMut<Tilemap>,
+    preset: Res<WorldGenPreset>,
+) {
+    commands.remove_resource::<bevy::ecs::event::EventUpdateSignal>();
+    info!(
+        "Building {} world with seed {}",
+        preset.name,
+        game_rng.seed()
+    );
+    *world_noise = WorldNoise::with_size(map_settings.size, &mut game_rng.terrain);
+    *navmesh = navmesh::components::Navmesh::new(map_settings.size);
+
+    let mut camera_bundle = Camera2dBundle::default();
+
+    camera_bundle.projection.scale = 0.50;
+    camera_bundle.transform.translation = Vec3::new(
+        map_settings.world_size() / 2.,
+        map_settings.world_size() / 2.,
+        0.,
+    );
+
+    commands.spawn((
+        CameraMetadata {
+            target: camera_bundle.transform.translation,
+            zoom: camera_bundle.projection.scale,
+            selection_world_bounds: None,
+        },
+        camera_bundle,
+        InputManagerBundle::<Input> {
+            input_map: InputMap::default()
+                .insert(
+                    VirtualDPad {
+                        up: KeyCode::W.into(),
+                        down: KeyCode::S.into(),
+                        left: KeyCode::A.into(),
+                        right: KeyCode::D.into(),
+                    },
+                    Input::Pan,
+                )
+                .insert(
+                    VirtualAxis {
+                        positive: MouseWheelDirection::Up.into(),
+                        negative: MouseWheelDirection::Down.into(),
+                    },
+                    Input::Zoom,
+                )
+                .insert(MouseButton::Left, Input::Select)
+                .insert(KeyCode::Grave, Input::Debug)
+                .insert(KeyCode::Escape, Input::Pause)
+                .insert(KeyCode::Numpad0, Input::DebugSpawnPawn)
+                .build(),
+            ..default()
+        },
+    ));
+
+    worldgen::generate_noise(&mut world_noise, &preset);
+
+    // the ground itself is drawn in chunks by the tilemap once it sees the new tiles
+    *tilemap = Tilemap::new(worldgen::classify_tiles(&world_noise.base_world, &preset));
+
+    for (pos, tile_type) in tilemap.tiles().iter() {
+        match tile_type {
+            TileType::Water => navmesh.set_walkable(pos, false),
+            TileType::Dirt => {
+                navmesh.set_walkable(pos, true);
+                navmesh.set_weight(pos, 1.);
+            }
+            TileType::Grass => {
+                navmesh.set_walkable(pos, true);
+                navmesh.set_weight(pos, 2.);
+            }
+        }
+    }
+}
+
+fn camera_interactions(
+    mut camera_query: Query<
+        (
+            &mut OrthographicProjection,
+            &mut Transform,
+            &mut CameraMetadata,
+            &Camera,
+            &GlobalTransform,
+        ),
+        With<Camera>,
+    >,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    input: Query<&ActionState<Input>>,
+    time: Res<Time>,
+    map_settings: Res<MapSettings>,
+) {
+    let delta = time.delta_seconds();
+    let Ok((mut projection, mut transform, mut camera_target, camera, global_camera_transform)) =
+        camera_query.get_single_mut()
+    else {
+        return;
+    };
+
+    let Ok(input) = input.get_single() else {
+        return;
+    };
+
+    let Some(camera_movement) = input.axis_pair(Input::Pan) else {
+        return;
+    };
+
+    let camera_movement = camera_movement.xy().normalize_or_zero();
+    let camera_zoom = -input.clamped_value(Input::Zoom) * 0.125;
+
+    camera_target.target += camera_movement.extend(0.) * delta * 1000. * projection.scale;
+    camera_target.target = camera_target.target.clamp(
+        Vec3::ZERO,
+        Vec3::new(map_settings.world_size(), map_settings.world_size(), 0.),
+    );
+    camera_target.zoom += camera_zoom * delta * 100.;
+    camera_target.zoom = camera_target.zoom.clamp(0.1, 2.5);
+
+    transform.translation = transform
+        .translation
+        .lerp(camera_target.target, 10. * delta);
+
+    projection.scale = projection.scale.lerp(&camera_target.zoom, &(10. * delta));
+
+    // Check if select is held to indicate a selection drag
+    if input.pressed(Input::Select) {
+        // get raw current mouse position
+        let Ok(window) = q_window.get_single() else {
+            return;
+        };
+        let Some(cursor_position) = window.cursor_position() else {
+            return;
+        };
+        let Some(world_pos) = camera
+            .viewport_to_world(global_camera_transform, cursor_position)
+            .map(|ray| ray.origin.truncate())
+        else {
+            return;
+        };
+
+        if let Some(bounds) = &mut camera_target.selection_world_bounds {
+            // if we already have a selection, update the second point
+            bounds.1 = world_pos;
+        } else {
+            // if we don't have a selection, create one
+            camera_target.selection_world_bounds = Some((world_pos, world_pos));
+        }
+    } else {
+        camera_target.selection_world_bounds = None;
+    }
+}
+
+fn update_cursor_position(
+    mut cursor_world_position: ResMut<CursorPosition>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform)>,
+) {
+    let Ok((camera, camera_transform)) = q_camera.get_single() else {
+        return;
+    };
+
+    let Ok(window) = q_window.get_single() else {
+        return;
+    };
+
+    let Some(cursor_position) = window.cursor_position() else {
+        return;
+    };
+
+    let world_pos = camera
+        .viewport_to_world(camera_transform, cursor_position)
+        .map(|ray| ray.origin.truncate());
+
+    cursor_world_position.0 = world_pos.map(|v| WorldPos(v).tile());
+}
+
+// Show a white box where the user is dragging to select
+fn selection_gizmo(mut gizmos: Gizmos, camera_metadata: Query<&CameraMetadata, With<Camera>>) {
+    let Ok(camera_metadata) = camera_metadata.get_single() else {
+        return;
+    };
+
+    if let Some(bounds) = &camera_metadata.selection_world_bounds {
+        let (start, end) = bounds;
+        let start = start.extend(0.);
+        let end = end.extend(0.);
+
+        let min = Vec3::new(start.x.min(end.x), start.y.min(end.y), 0.);
+        let max = Vec3::new(start.x.max(end.x), start.y.max(end.y), 0.);
+
+        let position = (min + max) / 2.0;
+        let size = max - min;
+        let color = Color::WHITE;
+
+        gizmos.rect_2d(position.truncate(), 0., size.truncate(), color);
+    }
+}
+
+fn toggle_paused(
+    mut change_game_state: ResMut<NextState<GameState>>,
+    game_state: Res<State<GameState>>,
+    input: Query<&ActionState<Input>>,
+) {
+    let Ok(input) = input.get_single() else {
+        return;
+    };
+
+    if input.just_pressed(Input::Pause) {
+        match game_state.get() {
+            GameState::Paused => change_game_state.set(GameState::Main),
+            GameState::Main => change_game_state.set(GameState::Paused),
+            unknown_state => {
+                error!(
+                    "Unexpected game state when toggling pause: {:?}",
+                    unknown_state
+                );
+            }
+        }
+    }
+}
@@ -1,6 +1,22 @@
-use bevy::{prelude::*, utils::HashSet};
-
-use crate::SIZE;
+use super::{
+    hierarchy::Hierarchy,
+    pathfinder::{heuristic, CostOverrides, PathOptions, Pathfinder, DIAGONAL_COST},
+    regions::Regions,
+};
+use crate::{grid::Grid, utils::TilePos};
+use bevy::{
+    prelude::*,
+    tasks::Task,
+    utils::{HashMap, HashSet},
+};
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 #[derive(Debug, Default, Resource)]
 pub struct ToggleNavmeshDebug(pub bool);
@@ -12,27 +28,446 @@ pub struct NavTileOccupant {
     pub walkable: bool,
 }
 
-#[derive(Resource)]
-pub struct Navmesh(pub [[NavTileOccupant; SIZE]; SIZE]);
+/// Which side a pawn is on, which decides the costs its paths are searched with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Faction {
+    #[default]
+    Colony,
+    Enemy,
+}
+
+/// Tunables shared by every pathfinding query
+#[derive(Debug, Resource)]
+pub struct PathfindingSettings {
+    /// Whether paths may step diagonally. Diagonal steps are never allowed to cut the corner of an
+    /// unwalkable tile, so pawns can't squeeze between two stones touching at a corner.
+    pub allow_diagonals: bool,
+    /// How many queued requests may be handed to the task pool each frame
+    pub max_solves_per_frame: usize,
+    /// How many tiles a single search may expand before giving up on an unreachable goal
+    pub max_search_nodes: usize,
+    /// Tile weights which differ for a faction, for example to keep enemies away from defenses
+    pub faction_costs: HashMap<Faction, Arc<CostOverrides>>,
+}
 
-impl Default for Navmesh {
+impl Default for PathfindingSettings {
     fn default() -> Self {
-        Self(std::array::from_fn(|_| {
-            std::array::from_fn(|_| NavTileOccupant::default())
-        }))
+        Self {
+            allow_diagonals: true,
+            max_solves_per_frame: 32,
+            max_search_nodes: 50_000,
+            faction_costs: default(),
+        }
+    }
+}
+
+impl PathfindingSettings {
+    /// The options paths for `faction` are searched with
+    pub fn options(&self, faction: Faction) -> PathOptions {
+        PathOptions {
+            allow_diagonals: self.allow_diagonals,
+            max_nodes: Some(self.max_search_nodes),
+            cost_overrides: self.faction_costs.get(&faction).cloned(),
+            ..default()
+        }
+    }
+}
+
+/// What the path solver needs to know about a tile, implemented by both the live navmesh and its snapshot
+pub trait NavTile {
+    fn walkable(&self) -> bool;
+    fn weight(&self) -> f32;
+}
+
+impl NavTile for NavTileOccupant {
+    fn walkable(&self) -> bool {
+        self.walkable
+    }
+
+    fn weight(&self) -> f32 {
+        self.weight
+    }
+}
+
+/// A copy of the walkability and weight of a navmesh tile, without the occupying entities
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct NavCell {
+    pub walkable: bool,
+    pub weight: f32,
+}
+
+impl NavTile for NavCell {
+    fn walkable(&self) -> bool {
+        self.walkable
+    }
+
+    fn weight(&self) -> f32 {
+        self.weight
+    }
+}
+
+/// A read-only copy of the navmesh which background pathfinding tasks solve against. It is rebuilt whenever
+/// the navmesh changes, and tasks which are already running keep the copy they started with.
+#[derive(Resource, Default)]
+pub struct NavmeshSnapshot(pub Arc<Grid<NavCell>>);
+
+/// The cluster graph used to solve long paths, along with the size of the map it was built for
+#[derive(Resource, Default)]
+pub struct NavmeshHierarchy {
+    pub graph: Arc<Hierarchy>,
+    pub(super) size: (usize, usize),
+}
+
+/// The cost to the nearest goal and the next step toward it for every tile
+pub(super) type FlowFieldLayers = (Grid<u32>, Grid<Option<TilePos>>);
+
+/// A shared field of directions toward a set of goal tiles, used to move many entities to the same place
+/// without solving a path for each of them. Every reachable tile knows its cost to the nearest goal and which
+/// neighbor to step onto next. The field is recomputed in the background whenever the goals or the navmesh change.
+#[derive(Resource, Default)]
+pub struct FlowField {
+    goals: Vec<TilePos>,
+    pub(super) costs: Grid<u32>,
+    pub(super) next: Grid<Option<TilePos>>,
+    pub(super) task: Option<Task<FlowFieldLayers>>,
+    pub(super) dirty: bool,
+}
+
+impl FlowField {
+    pub fn goals(&self) -> &[TilePos] {
+        &self.goals
     }
+
+    pub fn set_goals(&mut self, goals: impl IntoIterator<Item = TilePos>) {
+        self.goals = goals.into_iter().collect();
+        self.dirty = true;
+    }
+
+    /// The cost of the cheapest route from `pos` to a goal, or `None` if no goal can be reached from it
+    pub fn cost(&self, pos: TilePos) -> Option<u32> {
+        self.costs
+            .get(pos)
+            .copied()
+            .filter(|&cost| cost != u32::MAX)
+    }
+
+    /// The tile to step onto from `pos` to get closer to a goal. `None` on goals and unreachable tiles
+    pub fn next_step(&self, pos: TilePos) -> Option<TilePos> {
+        self.next.get(pos).copied().flatten()
+    }
+}
+
+/// The walkability and weight of every tile, and which entities occupy it. Tiles can only be changed through
+/// the methods below, which keep track of the tiles whose walkability or weight changed so a [`NavmeshChanged`]
+/// event can be sent for them, and so the connected regions can be updated once per frame.
+#[derive(Resource, Default)]
+pub struct Navmesh {
+    tiles: Grid<NavTileOccupant>,
+    changed: HashSet<TilePos>,
+    regions: Regions,
 }
 
+impl Navmesh {
+    pub fn new(size: usize) -> Self {
+        Self {
+            tiles: Grid::new(size, size),
+            changed: HashSet::default(),
+            regions: Regions::default(),
+        }
+    }
+
+    pub fn tiles(&self) -> &Grid<NavTileOccupant> {
+        &self.tiles
+    }
+
+    pub fn get(&self, pos: TilePos) -> Option<&NavTileOccupant> {
+        self.tiles.get(pos)
+    }
+
+    pub fn set_walkable(&mut self, pos: TilePos, walkable: bool) {
+        if let Some(tile) = self.tiles.get_mut(pos) {
+            if tile.walkable != walkable {
+                tile.walkable = walkable;
+                self.changed.insert(pos);
+            }
+        }
+    }
+
+    pub fn set_weight(&mut self, pos: TilePos, weight: f32) {
+        if let Some(tile) = self.tiles.get_mut(pos) {
+            if tile.weight != weight {
+                tile.weight = weight;
+                self.changed.insert(pos);
+            }
+        }
+    }
+
+    pub fn occupy(&mut self, pos: TilePos, entity: Entity) {
+        if let Some(tile) = self.tiles.get_mut(pos) {
+            tile.occupied_by.insert(entity);
+        }
+    }
+
+    pub fn vacate(&mut self, pos: TilePos, entity: Entity) {
+        if let Some(tile) = self.tiles.get_mut(pos) {
+            tile.occupied_by.remove(&entity);
+        }
+    }
+
+    /// Searches paths over the live tiles. Prefer the async [`PathfindRequest`] for anything but short searches
+    /// which need an answer this frame.
+    pub fn pathfinder(&self, options: PathOptions) -> Pathfinder<'_, NavTileOccupant> {
+        Pathfinder::new(&self.tiles, options)
+    }
+
+    /// The connected region a walkable tile belongs to
+    pub fn region(&self, pos: TilePos) -> Option<u32> {
+        self.regions.region(pos)
+    }
+
+    /// Whether a path exists between `from` and `to`. Unwalkable tiles such as stones count as reachable from
+    /// the walkable tiles next to them, the same way a path is allowed to end on one.
+    pub fn is_reachable(&self, from: TilePos, to: TilePos) -> bool {
+        self.access_regions(from)
+            .any(|region| self.access_regions(to).any(|other| other == region))
+    }
+
+    /// The region of `pos`, or the regions next to it if it's unwalkable
+    fn access_regions(&self, pos: TilePos) -> impl Iterator<Item = u32> + '_ {
+        let region = self.region(pos);
+        let neighbors = region
+            .is_none()
+            .then(|| self.tiles.neighbors_4(pos))
+            .into_iter()
+            .flatten()
+            .filter_map(|neighbor| self.region(neighbor));
+
+        region.into_iter().chain(neighbors)
+    }
+
+    /// Updates the regions for the tiles changed since the last call and returns them
+    pub(super) fn flush_changes(&mut self) -> Vec<TilePos> {
+        let changed = self.changed.drain().collect::<Vec<_>>();
+        if !changed.is_empty() {
+            self.regions.update(&self.tiles, &changed);
+        }
+
+        changed
+    }
+}
+
+/// Sent once per frame with every tile whose walkability or weight changed since the last one
 #[derive(Debug, Event)]
+pub struct NavmeshChanged {
+    pub tiles: Vec<TilePos>,
+}
+
+/// Where a path should lead. A path may end on any tile of the goal, which lets many pawns heading to the same
+/// building spread out around it instead of queueing for a single tile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathGoal {
+    /// A single tile. It's allowed to be unwalkable, like a stone being mined, in which case the path ends on it
+    Tile(TilePos),
+    /// Any walkable tile touching the outside of the footprint spanned by `min` and `max`, such as a building
+    Around { min: TilePos, max: TilePos },
+    /// Any walkable tile within `radius` tiles of `center`
+    Near { center: TilePos, radius: i32 },
+}
+
+impl PathGoal {
+    pub fn contains(&self, pos: TilePos) -> bool {
+        match *self {
+            Self::Tile(tile) => pos == tile,
+            Self::Around { min, max } => {
+                let inside = pos.0.cmpge(min.0).all() && pos.0.cmple(max.0).all();
+                let touching =
+                    pos.0.cmpge(min.0 - IVec2::ONE).all() && pos.0.cmple(max.0 + IVec2::ONE).all();
+                touching && !inside
+            }
+            Self::Near { center, radius } => (pos.0 - center.0).length_squared() <= radius * radius,
+        }
+    }
+
+    /// Whether a path may end on `pos` even though it isn't walkable
+    pub fn allows_unwalkable(&self, pos: TilePos) -> bool {
+        matches!(*self, Self::Tile(tile) if tile == pos)
+    }
+
+    /// A lower bound on the cost of reaching the goal from `pos`, so it can be used as an A* heuristic
+    pub fn heuristic(&self, pos: TilePos, allow_diagonals: bool) -> u32 {
+        match *self {
+            Self::Tile(tile) => heuristic(pos, tile, allow_diagonals),
+            Self::Around { min, max } => {
+                let nearest = pos.0.clamp(min.0 - IVec2::ONE, max.0 + IVec2::ONE);
+                heuristic(pos, TilePos(nearest), allow_diagonals)
+            }
+            // no step covers more distance than a diagonal one
+            Self::Near { center, radius } => heuristic(pos, center, allow_diagonals)
+                .saturating_sub(radius.max(0) as u32 * DIAGONAL_COST),
+        }
+    }
+}
+
+/// How urgently a [`PathfindRequest`] should be solved. Queued requests are handed to the task pool highest
+/// priority first, and in the order they were made within the same priority.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PathPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+#[derive(Debug, Clone, Copy, Event)]
 pub struct PathfindRequest {
-    pub start: Vec2,
-    pub end: Vec2,
+    pub id: PathRequestId,
+    pub start: TilePos,
+    pub goal: PathGoal,
     pub entity: Entity,
+    pub priority: PathPriority,
+    /// Whether to drop the waypoints which can be skipped by walking in a straight line, see
+    /// [`Pathfinder::smooth_path`]
+    pub smooth: bool,
 }
 
+impl PathfindRequest {
+    /// A request with a fresh ID, which the requesting entity can keep to recognize the answer meant for it
+    pub fn new(start: TilePos, goal: PathGoal, entity: Entity) -> Self {
+        Self {
+            id: PathRequestId::next(),
+            start,
+            goal,
+            entity,
+            priority: default(),
+            smooth: false,
+        }
+    }
+
+    pub fn with_priority(mut self, priority: PathPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn smoothed(mut self) -> Self {
+        self.smooth = true;
+        self
+    }
+
+    /// The same request made again from `start`, with a fresh ID
+    pub fn retry_from(self, start: TilePos) -> Self {
+        Self {
+            id: PathRequestId::next(),
+            start,
+            ..self
+        }
+    }
+}
+
+/// Identifies a single [`PathfindRequest`]. IDs increase with every request, so the latest request made by an
+/// entity always has the highest ID.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PathRequestId(pub u64);
+
+impl PathRequestId {
+    fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Drops the request `id` of `entity`, for when it no longer needs that path
 #[derive(Debug, Event)]
-pub struct PathfindAnswer {
-    pub path: Option<Vec<Vec2>>,
+pub struct CancelPathfinding {
     pub entity: Entity,
-    pub target: Vec2,
+    pub id: PathRequestId,
+}
+
+/// The path found for `request`, or `None` if its goal can't be reached
+#[derive(Debug, Event)]
+pub struct PathfindAnswer {
+    pub request: PathfindRequest,
+    pub path: Option<Vec<TilePos>>,
+}
+
+pub(super) struct PathfindTask {
+    pub request: PathfindRequest,
+    pub task: Task<Option<Vec<TilePos>>>,
+}
+
+/// Requests waiting for a slot in the per-frame budget, and the tasks solving the ones which got one. Only the
+/// latest request from each entity is answered, anything older is dropped as stale.
+#[derive(Resource, Default)]
+pub struct PathfindingQueue {
+    pub(super) pending: BinaryHeap<QueuedRequest>,
+    pub(super) in_flight: Vec<PathfindTask>,
+    latest: HashMap<Entity, PathRequestId>,
+}
+
+/// A request waiting in the [`PathfindingQueue`], ordered so the one to solve next is the greatest
+pub(super) struct QueuedRequest {
+    pub request: PathfindRequest,
+    pub faction: Faction,
+}
+
+impl QueuedRequest {
+    fn key(&self) -> (PathPriority, Reverse<PathRequestId>) {
+        (self.request.priority, Reverse(self.request.id))
+    }
+}
+
+impl PartialEq for QueuedRequest {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for QueuedRequest {}
+
+impl PartialOrd for QueuedRequest {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedRequest {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl PathfindingQueue {
+    /// Queues `request` unless its entity already made a newer one. Whatever the entity requested before is
+    /// superseded, and a task still solving it is dropped, which cancels it.
+    pub fn push(&mut self, request: PathfindRequest, faction: Faction) {
+        let PathfindRequest { id, entity, .. } = request;
+        if self.latest.get(&entity).is_some_and(|&latest| latest > id) {
+            return;
+        }
+
+        self.latest.insert(entity, id);
+        self.in_flight.retain(|task| task.request.entity != entity);
+        self.pending.push(QueuedRequest { request, faction });
+    }
+
+    /// Forgets the request `id` of `entity`, unless it has been superseded by a newer one. A queued request is
+    /// skipped once it comes up, a running one is dropped.
+    pub fn cancel(&mut self, entity: Entity, id: PathRequestId) {
+        if !self.is_latest(entity, id) {
+            return;
+        }
+        self.latest.remove(&entity);
+        self.in_flight.retain(|task| task.request.id != id);
+    }
+
+    /// Whether `id` is still the newest request made by `entity`
+    pub fn is_latest(&self, entity: Entity, id: PathRequestId) -> bool {
+        self.latest.get(&entity) == Some(&id)
+    }
+
+    /// Forgets the entity once its latest request has been answered
+    pub(super) fn complete(&mut self, entity: Entity, id: PathRequestId) {
+        if self.is_latest(entity, id) {
+            self.latest.remove(&entity);
+        }
+    }
 }
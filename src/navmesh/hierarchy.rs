@@ -0,0 +1,442 @@
+use super::{
+    components::{NavTile, PathGoal},
+    pathfinder::{PathOptions, Pathfinder, STRAIGHT_COST},
+};
+use crate::{grid::Grid, utils::TilePos};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use pathfinding::prelude::astar;
+
+/// The width and height of a cluster in tiles
+pub const CLUSTER_SIZE: i32 = 16;
+/// Openings along a cluster border at least this wide get an entrance at both ends instead of one in the middle
+const WIDE_ENTRANCE: usize = 6;
+
+type Edges = HashMap<TilePos, Vec<(TilePos, u32)>>;
+
+/// The border a cluster shares with the next cluster to its right or above it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Border {
+    Right,
+    Top,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Cluster {
+    /// The entrance tiles on the edges of the cluster
+    nodes: Vec<TilePos>,
+    /// The cost of walking between entrances without leaving the cluster
+    edges: Edges,
+}
+
+/// An abstract graph over the navmesh for hierarchical pathfinding (HPA*). The map is split into square
+/// clusters, every walkable opening between two clusters gets entrance nodes on both sides, and the entrances of
+/// a cluster are connected by the cost of walking between them inside it. Long paths are searched over this much
+/// smaller graph and then refined to tiles one cluster at a time.
+#[derive(Debug, Clone, Default)]
+pub struct Hierarchy {
+    allow_diagonals: bool,
+    clusters: HashMap<IVec2, Cluster>,
+    /// The pairs of tiles facing each other across every border, keyed by the cluster on the left or bottom
+    transitions: HashMap<(IVec2, Border), Vec<(TilePos, TilePos)>>,
+    /// The single steps across borders between entrances
+    crossings: Edges,
+}
+
+fn cluster_of(pos: TilePos) -> IVec2 {
+    IVec2::new(
+        pos.x().div_euclid(CLUSTER_SIZE),
+        pos.y().div_euclid(CLUSTER_SIZE),
+    )
+}
+
+fn is_walkable<T: NavTile>(grid: &Grid<T>, pos: TilePos) -> bool {
+    grid.get(pos).is_some_and(|tile| tile.walkable())
+}
+
+impl Hierarchy {
+    pub fn build<T: NavTile>(grid: &Grid<T>, allow_diagonals: bool) -> Self {
+        let mut hierarchy = Self {
+            allow_diagonals,
+            ..default()
+        };
+
+        let clusters_x = (grid.width() as i32 + CLUSTER_SIZE - 1) / CLUSTER_SIZE;
+        let clusters_y = (grid.height() as i32 + CLUSTER_SIZE - 1) / CLUSTER_SIZE;
+        let clusters = (0..clusters_y)
+            .flat_map(|y| (0..clusters_x).map(move |x| IVec2::new(x, y)))
+            .collect::<Vec<_>>();
+
+        for &cluster in &clusters {
+            hierarchy.rebuild_border(grid, cluster, Border::Right);
+            hierarchy.rebuild_border(grid, cluster, Border::Top);
+        }
+        for &cluster in &clusters {
+            hierarchy.rebuild_cluster(grid, cluster);
+        }
+
+        hierarchy
+    }
+
+    pub fn allow_diagonals(&self) -> bool {
+        self.allow_diagonals
+    }
+
+    /// The cluster graph is built from the plain navmesh costs, without any per faction overrides
+    fn pathfinder<'a, T: NavTile>(&self, grid: &'a Grid<T>) -> Pathfinder<'a, T> {
+        Pathfinder::new(
+            grid,
+            PathOptions {
+                allow_diagonals: self.allow_diagonals,
+                ..default()
+            },
+        )
+    }
+
+    /// Brings the graph up to date after the walkability or weight of `changed` tiles changed in `grid`. Only the
+    /// borders and clusters around those tiles are rebuilt.
+    pub fn update<T: NavTile>(
+        &mut self,
+        grid: &Grid<T>,
+        changed: impl IntoIterator<Item = TilePos>,
+    ) {
+        let mut borders = HashSet::new();
+        let mut clusters = HashSet::new();
+
+        for pos in changed {
+            let cluster = cluster_of(pos);
+            let local = pos.0 - cluster * CLUSTER_SIZE;
+
+            if local.x == CLUSTER_SIZE - 1 {
+                borders.insert((cluster, Border::Right));
+            }
+            if local.x == 0 {
+                borders.insert((cluster - IVec2::X, Border::Right));
+            }
+            if local.y == CLUSTER_SIZE - 1 {
+                borders.insert((cluster, Border::Top));
+            }
+            if local.y == 0 {
+                borders.insert((cluster - IVec2::Y, Border::Top));
+            }
+
+            // diagonal steps check the tiles beside them, so paths in the surrounding clusters may change too
+            clusters.insert(cluster);
+            clusters.extend(grid.neighbors_8(pos).map(cluster_of));
+        }
+
+        for (cluster, border) in borders {
+            if cluster.x >= 0 && cluster.y >= 0 {
+                self.rebuild_border(grid, cluster, border);
+            }
+        }
+        for cluster in clusters {
+            self.rebuild_cluster(grid, cluster);
+        }
+    }
+
+    /// Finds the entrances across a border and replaces the crossings previously found there. Each run of
+    /// tiles which are walkable on both sides gets one entrance in the middle, or two at its ends if it's wide.
+    fn rebuild_border<T: NavTile>(&mut self, grid: &Grid<T>, cluster: IVec2, border: Border) {
+        let origin = cluster * CLUSTER_SIZE;
+        let pairs = (0..CLUSTER_SIZE)
+            .map(|i| match border {
+                Border::Right => (
+                    TilePos::new(origin.x + CLUSTER_SIZE - 1, origin.y + i),
+                    TilePos::new(origin.x + CLUSTER_SIZE, origin.y + i),
+                ),
+                Border::Top => (
+                    TilePos::new(origin.x + i, origin.y + CLUSTER_SIZE - 1),
+                    TilePos::new(origin.x + i, origin.y + CLUSTER_SIZE),
+                ),
+            })
+            .map(|(a, b)| ((a, b), is_walkable(grid, a) && is_walkable(grid, b)))
+            .collect::<Vec<_>>();
+
+        let pathfinder = self.pathfinder(grid);
+        let mut transitions = Vec::new();
+        for run in pairs
+            .split(|&(_, open)| !open)
+            .filter(|run| !run.is_empty())
+        {
+            if run.len() >= WIDE_ENTRANCE {
+                transitions.push(run[0].0);
+                transitions.push(run[run.len() - 1].0);
+            } else {
+                transitions.push(run[run.len() / 2].0);
+            }
+        }
+
+        for (a, b) in self
+            .transitions
+            .remove(&(cluster, border))
+            .unwrap_or_default()
+        {
+            self.remove_crossing(a, b);
+            self.remove_crossing(b, a);
+        }
+
+        for &(a, b) in &transitions {
+            self.crossings
+                .entry(a)
+                .or_default()
+                .push((b, pathfinder.step_cost(a, b)));
+            self.crossings
+                .entry(b)
+                .or_default()
+                .push((a, pathfinder.step_cost(b, a)));
+        }
+
+        if !transitions.is_empty() {
+            self.transitions.insert((cluster, border), transitions);
+        }
+    }
+
+    fn remove_crossing(&mut self, from: TilePos, to: TilePos) {
+        if let Some(edges) = self.crossings.get_mut(&from) {
+            edges.retain(|&(pos, _)| pos != to);
+            if edges.is_empty() {
+                self.crossings.remove(&from);
+            }
+        }
+    }
+
+    /// Collects the entrances of a cluster from its four borders and connects every pair of them which can reach
+    /// each other inside the cluster
+    fn rebuild_cluster<T: NavTile>(&mut self, grid: &Grid<T>, cluster: IVec2) {
+        let sides = [
+            ((cluster, Border::Right), true),
+            ((cluster, Border::Top), true),
+            ((cluster - IVec2::X, Border::Right), false),
+            ((cluster - IVec2::Y, Border::Top), false),
+        ];
+
+        let mut nodes = sides
+            .iter()
+            .filter_map(|(key, near_side)| Some((self.transitions.get(key)?, *near_side)))
+            .flat_map(|(transitions, near_side)| {
+                transitions
+                    .iter()
+                    .map(move |&(a, b)| if near_side { a } else { b })
+            })
+            .collect::<Vec<_>>();
+        nodes.sort_by_key(|pos| (pos.x(), pos.y()));
+        nodes.dedup();
+
+        if nodes.is_empty() {
+            self.clusters.remove(&cluster);
+            return;
+        }
+
+        let mut edges = Edges::default();
+        for &node in &nodes {
+            let reached = self.flood_cluster(&self.pathfinder(grid), cluster, node);
+            let node_edges = nodes
+                .iter()
+                .filter_map(|other| Some((*other, reached.get(other)?.1)))
+                .collect();
+            edges.insert(node, node_edges);
+        }
+
+        self.clusters.insert(cluster, Cluster { nodes, edges });
+    }
+
+    /// The cost of reaching every walkable tile in a cluster from `start` without leaving it
+    fn flood_cluster<T: NavTile>(
+        &self,
+        pathfinder: &Pathfinder<T>,
+        cluster: IVec2,
+        start: TilePos,
+    ) -> HashMap<TilePos, (TilePos, u32)> {
+        pathfinder.reach_all(start, |neighbor| cluster_of(neighbor) == cluster)
+    }
+
+    /// Finds a path from `start` to `goal`. Short paths are searched directly on the grid. Longer ones are searched
+    /// over the cluster graph until an entrance close to the goal, refined to tiles, and finished with a direct
+    /// search from that entrance. This is much faster on large maps at the price of paths which are close to, but
+    /// not always exactly, the cheapest. The cluster graph only knows the plain navmesh costs, while refining uses
+    /// the options of `pathfinder`, which must allow diagonals exactly when the graph does.
+    pub fn find_path<T: NavTile>(
+        &self,
+        pathfinder: &Pathfinder<T>,
+        start: TilePos,
+        goal: &PathGoal,
+    ) -> Option<Vec<TilePos>> {
+        debug_assert_eq!(pathfinder.options().allow_diagonals, self.allow_diagonals);
+
+        let close_to_goal =
+            |pos| pathfinder.heuristic(pos, goal) <= CLUSTER_SIZE as u32 * STRAIGHT_COST;
+        if close_to_goal(start) {
+            return pathfinder.find_path(start, goal);
+        }
+
+        // temporarily connect the start to the entrances of its cluster
+        let start_cluster = cluster_of(start);
+        let start_reached = self.flood_cluster(pathfinder, start_cluster, start);
+        let start_edges = self
+            .clusters
+            .get(&start_cluster)
+            .map_or(&[][..], |cluster| &cluster.nodes[..])
+            .iter()
+            .filter_map(|node| Some((*node, start_reached.get(node)?.1)))
+            .collect::<Vec<_>>();
+
+        let (route, _) = astar(
+            &start,
+            |&pos| {
+                let mut edges = Vec::new();
+                if pos == start {
+                    edges.extend(start_edges.iter().copied());
+                }
+                if let Some(crossings) = self.crossings.get(&pos) {
+                    edges.extend(crossings.iter().copied());
+                }
+                if let Some(cluster) = self.clusters.get(&cluster_of(pos)) {
+                    edges.extend(cluster.edges.get(&pos).into_iter().flatten().copied());
+                }
+                edges
+            },
+            |&pos| pathfinder.heuristic(pos, goal),
+            |&pos| close_to_goal(pos),
+        )?;
+
+        // refine each step of the abstract route into tiles
+        let mut path = vec![start];
+        for step in route.windows(2) {
+            let (from, to) = (step[0], step[1]);
+
+            if cluster_of(from) != cluster_of(to) {
+                path.push(to);
+                continue;
+            }
+
+            let cluster = cluster_of(from);
+            let (segment, _) = pathfinder
+                .find_path_within(from, &PathGoal::Tile(to), |pos| cluster_of(pos) == cluster)?;
+            path.extend(segment.into_iter().skip(1));
+        }
+
+        let last = *path.last().unwrap();
+        let finish = pathfinder.find_path(last, goal)?;
+        path.extend(finish.into_iter().skip(1));
+
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navmesh::components::NavCell;
+
+    /// Builds a grid which is open ground except for the tiles `blocked` returns true for
+    fn grid(width: usize, height: usize, blocked: impl Fn(TilePos) -> bool) -> Grid<NavCell> {
+        Grid::from_fn(width, height, |pos| NavCell {
+            walkable: !blocked(pos),
+            weight: 1.,
+        })
+    }
+
+    fn pos(x: i32, y: i32) -> TilePos {
+        TilePos::new(x, y)
+    }
+
+    /// Checks that both hierarchies describe the same graph. The crossings of a tile may be listed in any order,
+    /// depending on which border was rebuilt first.
+    fn assert_same_graph(updated: &Hierarchy, built: &Hierarchy) {
+        let crossings = |hierarchy: &Hierarchy| {
+            hierarchy
+                .crossings
+                .iter()
+                .flat_map(|(&from, edges)| edges.iter().map(move |&(to, cost)| (from, to, cost)))
+                .collect::<HashSet<_>>()
+        };
+
+        assert_eq!(updated.transitions, built.transitions);
+        assert_eq!(updated.clusters, built.clusters);
+        assert_eq!(crossings(updated), crossings(built));
+    }
+
+    #[test]
+    fn matches_direct_search() {
+        // a wall splitting the map with a single gap at the top, far enough apart to search over clusters
+        let grid = grid(48, 40, |pos| pos.x() == 24 && pos.y() < 37);
+        let pathfinder = Pathfinder::new(&grid, PathOptions::default());
+        let hierarchy = Hierarchy::build(&grid, true);
+        let goal = PathGoal::Tile(pos(46, 0));
+
+        let path = hierarchy.find_path(&pathfinder, pos(1, 0), &goal).unwrap();
+        let direct = pathfinder.find_path(pos(1, 0), &goal).unwrap();
+
+        for step in path.windows(2) {
+            let offset = step[1].0 - step[0].0;
+            assert!(offset.x.abs() <= 1 && offset.y.abs() <= 1 && offset != IVec2::ZERO);
+        }
+        assert!(path.iter().all(|&tile| is_walkable(&grid, tile)));
+        assert_eq!(path.first(), Some(&pos(1, 0)));
+        assert_eq!(path.last(), Some(&pos(46, 0)));
+        assert!(path.iter().any(|&tile| tile.x() == 24));
+        assert!(path.len() <= direct.len() * 3 / 2);
+    }
+
+    #[test]
+    fn follows_navmesh_changes() {
+        let mut grid = grid(40, 8, |_| false);
+        let mut hierarchy = Hierarchy::build(&grid, true);
+        let goal = PathGoal::Tile(pos(38, 4));
+
+        let wall = (0..8).map(|y| pos(20, y)).collect::<Vec<_>>();
+        for &tile in &wall {
+            grid.get_mut(tile).unwrap().walkable = false;
+        }
+        hierarchy.update(&grid, wall);
+
+        let pathfinder = Pathfinder::new(&grid, PathOptions::default());
+        assert_eq!(hierarchy.find_path(&pathfinder, pos(1, 4), &goal), None);
+    }
+
+    #[test]
+    fn update_matches_fresh_build() {
+        // the right border of the bottom left cluster and the top border of the bottom right one are walled off,
+        // apart from a few narrow and wide openings
+        let mut grid = grid(48, 32, |pos| {
+            let right = pos.x() == CLUSTER_SIZE
+                && ![4, 5, 6, 10].contains(&pos.y())
+                && !(20..=29).contains(&pos.y());
+            let top = pos.y() == CLUSTER_SIZE
+                && pos.x() >= 2 * CLUSTER_SIZE
+                && !(36..=38).contains(&pos.x());
+            right || top
+        });
+        let mut hierarchy = Hierarchy::build(&grid, true);
+
+        let mut set_walkable = |changed: &[TilePos], walkable: bool| {
+            for &tile in changed {
+                grid.get_mut(tile).unwrap().walkable = walkable;
+            }
+            hierarchy.update(&grid, changed.iter().copied());
+            assert_same_graph(&hierarchy, &Hierarchy::build(&grid, true));
+        };
+
+        // block openings from either side of both borders, one side at a time
+        set_walkable(&[pos(16, 5), pos(16, 6)], false);
+        set_walkable(&[pos(15, 10)], false);
+        set_walkable(&[pos(37, 16)], false);
+        set_walkable(&[pos(38, 15)], false);
+        // narrow a wide opening, which moves its entrances
+        set_walkable(&[pos(16, 20), pos(16, 21)], false);
+        // and reopen what was closed
+        set_walkable(
+            &[
+                pos(16, 5),
+                pos(16, 6),
+                pos(15, 10),
+                pos(37, 16),
+                pos(38, 15),
+            ],
+            true,
+        );
+    }
+}
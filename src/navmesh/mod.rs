@@ -1,9 +1,14 @@
 pub mod components;
+pub mod hierarchy;
+pub mod pathfinder;
+pub mod regions;
 pub mod systems;
 
-use self::components::{Navmesh, PathfindAnswer, PathfindRequest, ToggleNavmeshDebug};
+use self::components::{
+    CancelPathfinding, FlowField, Navmesh, NavmeshChanged, NavmeshHierarchy, NavmeshSnapshot,
+    PathfindAnswer, PathfindRequest, PathfindingQueue, PathfindingSettings, ToggleNavmeshDebug,
+};
 use bevy::prelude::*;
-pub use systems::get_pathing;
 
 #[derive(SystemSet, Hash, Debug, Clone, Eq, PartialEq)]
 pub enum NavmeshSystemSet {
@@ -18,6 +23,11 @@ impl Plugin for NavmeshPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Navmesh>()
             .init_resource::<ToggleNavmeshDebug>()
+            .init_resource::<PathfindingSettings>()
+            .init_resource::<NavmeshSnapshot>()
+            .init_resource::<PathfindingQueue>()
+            .init_resource::<FlowField>()
+            .init_resource::<NavmeshHierarchy>()
             .configure_sets(
                 Update,
                 (
@@ -27,15 +37,35 @@ impl Plugin for NavmeshPlugin {
                 )
                     .chain(),
             )
+            .add_systems(
+                Update,
+                systems::send_navmesh_changes.in_set(NavmeshSystemSet::First),
+            )
             .add_systems(
                 Update,
                 (
                     systems::debug_navmesh,
-                    systems::listen_for_pathfinding_requests,
+                    systems::update_navmesh_snapshot.run_if(resource_changed::<Navmesh>()),
+                    systems::update_navmesh_hierarchy
+                        .after(systems::update_navmesh_snapshot)
+                        .run_if(
+                            on_event::<NavmeshChanged>()
+                                .or_else(resource_changed::<PathfindingSettings>()),
+                        ),
+                    (
+                        systems::listen_for_pathfinding_requests,
+                        systems::dispatch_pathfinding_tasks,
+                        systems::poll_pathfinding_tasks,
+                    )
+                        .chain()
+                        .after(systems::update_navmesh_hierarchy),
+                    systems::update_flow_field.after(systems::update_navmesh_snapshot),
                 )
                     .in_set(NavmeshSystemSet::Update),
             )
             .add_event::<PathfindRequest>()
-            .add_event::<PathfindAnswer>();
+            .add_event::<PathfindAnswer>()
+            .add_event::<CancelPathfinding>()
+            .add_event::<NavmeshChanged>();
     }
 }
@@ -0,0 +1,662 @@
+use super::components::{NavTile, PathGoal};
+use crate::grid::{Grid, NEIGHBORS_4, NEIGHBORS_8};
+use crate::utils::TilePos;
+use bevy::{prelude::*, utils::HashMap};
+use pathfinding::prelude::*;
+use std::{cell::Cell, sync::Arc};
+
+/// The cost of stepping straight onto a tile of weight 1. Costs are kept as integers so A* stays exact,
+/// with a diagonal step costing roughly `STRAIGHT_COST * sqrt(2)`
+pub const STRAIGHT_COST: u32 = 10;
+pub const DIAGONAL_COST: u32 = 14;
+/// Weights below this are clamped up to it, which keeps the octile heuristic admissible
+const MIN_WEIGHT: f32 = 1.;
+
+/// Tile weights which replace the navmesh weight for one faction, such as enemies steering clear of turrets
+pub type CostOverrides = HashMap<TilePos, f32>;
+
+/// How a [`Pathfinder`] is allowed to search
+#[derive(Debug, Clone)]
+pub struct PathOptions {
+    /// Whether paths may step diagonally. Diagonal steps are never allowed to cut the corner of an
+    /// unwalkable tile, so pawns can't squeeze between two stones touching at a corner.
+    pub allow_diagonals: bool,
+    /// Whether a search may start on an unwalkable tile, like a pawn standing where something was just built
+    pub allow_blocked_start: bool,
+    /// Whether a path may end on an unwalkable [`PathGoal::Tile`], like a stone being mined
+    pub allow_blocked_goal: bool,
+    /// Searches give up after expanding this many tiles, which bounds the cost of hopeless requests
+    pub max_nodes: Option<usize>,
+    /// Weights used instead of the navmesh weight of individual tiles
+    pub cost_overrides: Option<Arc<CostOverrides>>,
+}
+
+impl Default for PathOptions {
+    fn default() -> Self {
+        Self {
+            allow_diagonals: true,
+            allow_blocked_start: true,
+            allow_blocked_goal: true,
+            max_nodes: None,
+            cost_overrides: None,
+        }
+    }
+}
+
+/// The octile distance between two tiles at the cheapest possible weight, so it never overestimates
+pub fn heuristic(from: TilePos, to: TilePos, allow_diagonals: bool) -> u32 {
+    let dx = from.x().abs_diff(to.x());
+    let dy = from.y().abs_diff(to.y());
+
+    if allow_diagonals {
+        STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+    } else {
+        STRAIGHT_COST * (dx + dy)
+    }
+}
+
+/// The tiles a straight line from the center of `from` to the center of `to` passes through, excluding `from`.
+/// Where the line passes exactly through a corner both tiles beside it are included, the same way a diagonal step
+/// needs both of them to be walkable.
+pub fn line_tiles(from: TilePos, to: TilePos) -> Vec<TilePos> {
+    let delta = to.0 - from.0;
+    let (nx, ny) = (delta.x.abs(), delta.y.abs());
+    let step = delta.signum();
+    let (mut ix, mut iy) = (0, 0);
+    let mut pos = from;
+    let mut tiles = Vec::new();
+
+    while ix < nx || iy < ny {
+        // which tile border the line crosses next, found by comparing the crossings along each axis
+        let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+        if decision == 0 {
+            tiles.push(pos + IVec2::new(step.x, 0));
+            tiles.push(pos + IVec2::new(0, step.y));
+            pos = pos + step;
+            ix += 1;
+            iy += 1;
+        } else if decision < 0 {
+            pos = pos + IVec2::new(step.x, 0);
+            ix += 1;
+        } else {
+            pos = pos + IVec2::new(0, step.y);
+            iy += 1;
+        }
+        tiles.push(pos);
+    }
+
+    tiles
+}
+
+/// Every path query over a grid of [`NavTile`]s goes through here, so paths, floods and the cluster graph all agree
+/// on which steps are allowed and what they cost
+pub struct Pathfinder<'a, T> {
+    grid: &'a Grid<T>,
+    options: PathOptions,
+}
+
+impl<'a, T: NavTile> Pathfinder<'a, T> {
+    pub fn new(grid: &'a Grid<T>, options: PathOptions) -> Self {
+        Self { grid, options }
+    }
+
+    pub fn options(&self) -> &PathOptions {
+        &self.options
+    }
+
+    pub fn is_walkable(&self, pos: TilePos) -> bool {
+        self.grid.get(pos).is_some_and(|tile| tile.walkable())
+    }
+
+    fn weight(&self, pos: TilePos) -> f32 {
+        let weight = self
+            .options
+            .cost_overrides
+            .as_ref()
+            .and_then(|overrides| overrides.get(&pos).copied())
+            .or_else(|| self.grid.get(pos).map(|tile| tile.weight()))
+            .unwrap_or(MIN_WEIGHT);
+
+        weight.max(MIN_WEIGHT)
+    }
+
+    /// The cost of moving from `from` onto `to`, scaled by the weight of the tile being entered
+    pub fn step_cost(&self, from: TilePos, to: TilePos) -> u32 {
+        let base = if from.x() != to.x() && from.y() != to.y() {
+            DIAGONAL_COST
+        } else {
+            STRAIGHT_COST
+        };
+
+        (base as f32 * self.weight(to)).round() as u32
+    }
+
+    /// The tiles reachable in a single step from `pos` along with the cost of the step. `passable` decides which
+    /// tiles may be entered. Diagonal steps additionally need both of the tiles they pass between to be walkable.
+    pub fn successors(
+        &self,
+        pos: TilePos,
+        passable: impl Fn(TilePos) -> bool,
+    ) -> Vec<(TilePos, u32)> {
+        let offsets = if self.options.allow_diagonals {
+            &NEIGHBORS_8[..]
+        } else {
+            &NEIGHBORS_4[..]
+        };
+
+        offsets
+            .iter()
+            .map(|&offset| (pos + offset, offset))
+            .filter(|&(neighbor, _)| self.grid.contains(neighbor) && passable(neighbor))
+            .filter(|&(_, offset)| {
+                offset.x == 0
+                    || offset.y == 0
+                    || (self.is_walkable(pos + IVec2::new(offset.x, 0))
+                        && self.is_walkable(pos + IVec2::new(0, offset.y)))
+            })
+            .map(|(neighbor, _)| (neighbor, self.step_cost(pos, neighbor)))
+            .collect()
+    }
+
+    /// Whether walking straight from `from` to `to` only crosses walkable tiles no heavier than `max_weight`
+    pub fn line_is_clear(&self, from: TilePos, to: TilePos, max_weight: f32) -> bool {
+        line_tiles(from, to)
+            .into_iter()
+            .all(|pos| self.is_walkable(pos) && self.weight(pos) <= max_weight)
+    }
+
+    /// Drops the waypoints of `path` which can be skipped by walking straight from an earlier one, so pawns don't
+    /// zig-zag across open ground. A shortcut may only cross walkable tiles no heavier than the ones of the stretch
+    /// of path it replaces, which keeps paths from cutting through terrain they were routed around.
+    pub fn smooth_path(&self, path: Vec<TilePos>) -> Vec<TilePos> {
+        if path.len() < 3 {
+            return path;
+        }
+
+        let mut smoothed = vec![path[0]];
+        let mut anchor = path[0];
+        let mut max_weight = self.weight(path[1]);
+
+        for i in 1..path.len() - 1 {
+            max_weight = max_weight.max(self.weight(path[i + 1]));
+
+            if !self.line_is_clear(anchor, path[i + 1], max_weight) {
+                smoothed.push(path[i]);
+                anchor = path[i];
+                max_weight = self.weight(path[i + 1]);
+            }
+        }
+        smoothed.push(path[path.len() - 1]);
+
+        smoothed
+    }
+
+    /// A lower bound on the cost of reaching `goal` from `pos`
+    pub fn heuristic(&self, pos: TilePos, goal: &PathGoal) -> u32 {
+        goal.heuristic(pos, self.options.allow_diagonals)
+    }
+
+    /// Counts expanded tiles and tells whether the search is still within `max_nodes`
+    fn budget(&self) -> impl Fn() -> bool + '_ {
+        let expanded = Cell::new(0);
+        move || {
+            expanded.set(expanded.get() + 1);
+            expanded.get() <= self.options.max_nodes.unwrap_or(usize::MAX)
+        }
+    }
+
+    fn can_start(&self, start: TilePos) -> bool {
+        self.grid.contains(start) && (self.options.allow_blocked_start || self.is_walkable(start))
+    }
+
+    /// The cheapest path from `start` to any tile of `goal`, including both ends
+    pub fn find_path(&self, start: TilePos, goal: &PathGoal) -> Option<Vec<TilePos>> {
+        self.find_path_within(start, goal, |_| true)
+            .map(|(path, _)| path)
+    }
+
+    /// Like [`Self::find_path`], but never leaves the tiles `within` allows and also returns the cost of the path
+    pub fn find_path_within(
+        &self,
+        start: TilePos,
+        goal: &PathGoal,
+        within: impl Fn(TilePos) -> bool,
+    ) -> Option<(Vec<TilePos>, u32)> {
+        if !self.can_start(start) {
+            return None;
+        }
+
+        let within_budget = self.budget();
+        astar(
+            &start,
+            |&pos| {
+                if !within_budget() {
+                    return Vec::new();
+                }
+                self.successors(pos, |neighbor| {
+                    within(neighbor)
+                        && (self.is_walkable(neighbor)
+                            || (self.options.allow_blocked_goal
+                                && goal.allows_unwalkable(neighbor)))
+                })
+            },
+            |&pos| self.heuristic(pos, goal),
+            |&pos| goal.contains(pos),
+        )
+    }
+
+    /// The cheapest cost from `start` to every walkable tile `within` allows, along with the tile each one is
+    /// entered from
+    pub fn reach_all(
+        &self,
+        start: TilePos,
+        within: impl Fn(TilePos) -> bool,
+    ) -> HashMap<TilePos, (TilePos, u32)> {
+        if !self.can_start(start) {
+            return HashMap::default();
+        }
+
+        dijkstra_all(&start, |&pos| {
+            self.successors(pos, |neighbor| {
+                within(neighbor) && self.is_walkable(neighbor)
+            })
+        })
+        .into_iter()
+        .collect()
+    }
+
+    /// Floods outward from `start` in order of walking cost and returns the first tile matching `is_target` along
+    /// with the path to it. Targets don't have to be walkable, but the flood never passes through one which isn't.
+    /// Gives up once every tile within `max_cost` has been checked.
+    pub fn nearest_reachable(
+        &self,
+        start: TilePos,
+        max_cost: u32,
+        is_target: impl Fn(TilePos, &T) -> bool,
+    ) -> Option<(TilePos, Vec<TilePos>)> {
+        if !self.can_start(start) {
+            return None;
+        }
+
+        let is_target = |pos: TilePos| self.grid.get(pos).is_some_and(|tile| is_target(pos, tile));
+        let within_budget = self.budget();
+        let mut parents = HashMap::new();
+
+        let reached = dijkstra_reach(&start, |&pos, cost| {
+            // unwalkable targets are the end of the line
+            if (pos != start && !self.is_walkable(pos)) || !within_budget() {
+                return Vec::new();
+            }
+
+            let mut next = self.successors(pos, |neighbor| {
+                self.is_walkable(neighbor) || is_target(neighbor)
+            });
+            next.retain(|&(_, step)| cost + step <= max_cost);
+            next
+        })
+        .find(|item| {
+            parents.insert(item.node, item.parent);
+            is_target(item.node)
+        })?;
+
+        let mut path = vec![reached.node];
+        while let Some(&Some(parent)) = parents.get(path.last().unwrap()) {
+            path.push(parent);
+        }
+        path.reverse();
+
+        Some((reached.node, path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navmesh::components::NavCell;
+
+    /// Builds a grid from rows drawn top to bottom: `.` is open ground, `#` is blocked and a digit is open ground
+    /// of that weight
+    fn grid(rows: &[&str]) -> Grid<NavCell> {
+        let height = rows.len();
+        let width = rows[0].len();
+
+        Grid::from_fn(width, height, |pos| {
+            let row = rows[height - 1 - pos.y() as usize].as_bytes();
+            match row[pos.x() as usize] {
+                b'#' => NavCell {
+                    walkable: false,
+                    weight: 1.,
+                },
+                b'.' => NavCell {
+                    walkable: true,
+                    weight: 1.,
+                },
+                digit => NavCell {
+                    walkable: true,
+                    weight: (digit - b'0') as f32,
+                },
+            }
+        })
+    }
+
+    fn pos(x: i32, y: i32) -> TilePos {
+        TilePos::new(x, y)
+    }
+
+    fn straight() -> PathOptions {
+        PathOptions {
+            allow_diagonals: false,
+            ..default()
+        }
+    }
+
+    /// Checks that every step of `path` is a single move onto a tile the search was allowed to enter
+    fn assert_connected(path: &[TilePos], grid: &Grid<NavCell>) {
+        for step in path.windows(2) {
+            let offset = step[1].0 - step[0].0;
+            assert!(offset.x.abs() <= 1 && offset.y.abs() <= 1 && offset != IVec2::ZERO);
+        }
+        for &tile in &path[1..path.len() - 1] {
+            assert!(grid.get(tile).unwrap().walkable, "path crosses {tile:?}");
+        }
+    }
+
+    #[test]
+    fn finds_straight_path() {
+        let grid = grid(&["....."]);
+        let path = Pathfinder::new(&grid, straight())
+            .find_path(pos(0, 0), &PathGoal::Tile(pos(4, 0)))
+            .unwrap();
+
+        assert_eq!(path, (0..5).map(|x| pos(x, 0)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn walls_off_unreachable_goal() {
+        let grid = grid(&[
+            "..#..", //
+            "..#..", //
+            "..#..", //
+        ]);
+        let pathfinder = Pathfinder::new(&grid, PathOptions::default());
+
+        assert_eq!(
+            pathfinder.find_path(pos(0, 1), &PathGoal::Tile(pos(4, 1))),
+            None
+        );
+        assert_eq!(
+            pathfinder.nearest_reachable(pos(0, 1), u32::MAX, |tile, _| tile == pos(4, 1)),
+            None
+        );
+    }
+
+    #[test]
+    fn goal_outside_grid_is_unreachable() {
+        let grid = grid(&["..."]);
+        let path = Pathfinder::new(&grid, PathOptions::default())
+            .find_path(pos(0, 0), &PathGoal::Tile(pos(5, 0)));
+
+        assert_eq!(path, None);
+    }
+
+    #[test]
+    fn blocked_start_needs_permission() {
+        let grid = grid(&["#...."]);
+        let goal = PathGoal::Tile(pos(4, 0));
+
+        let allowed = Pathfinder::new(&grid, straight()).find_path(pos(0, 0), &goal);
+        assert_eq!(allowed.unwrap().len(), 5);
+
+        let denied = Pathfinder::new(
+            &grid,
+            PathOptions {
+                allow_blocked_start: false,
+                ..straight()
+            },
+        )
+        .find_path(pos(0, 0), &goal);
+        assert_eq!(denied, None);
+    }
+
+    #[test]
+    fn blocked_goal_needs_permission() {
+        let grid = grid(&["....#"]);
+        let goal = PathGoal::Tile(pos(4, 0));
+
+        let allowed = Pathfinder::new(&grid, straight()).find_path(pos(0, 0), &goal);
+        assert_eq!(allowed.unwrap().last(), Some(&pos(4, 0)));
+
+        let denied = Pathfinder::new(
+            &grid,
+            PathOptions {
+                allow_blocked_goal: false,
+                ..straight()
+            },
+        )
+        .find_path(pos(0, 0), &goal);
+        assert_eq!(denied, None);
+    }
+
+    #[test]
+    fn never_passes_through_blocked_goal_set() {
+        // only a single tile goal may be unwalkable, the footprint of a building never is part of a path
+        let grid = grid(&[
+            ".....", //
+            ".##..", //
+            ".##..", //
+            ".....", //
+        ]);
+        let goal = PathGoal::Around {
+            min: pos(1, 1),
+            max: pos(2, 2),
+        };
+        let path = Pathfinder::new(&grid, PathOptions::default())
+            .find_path(pos(4, 0), &goal)
+            .unwrap();
+
+        assert!(goal.contains(*path.last().unwrap()));
+        assert!(path.iter().all(|&tile| grid.get(tile).unwrap().walkable));
+    }
+
+    #[test]
+    fn detours_around_heavy_tiles() {
+        let grid = grid(&[
+            ".....", //
+            ".999.", //
+            ".....", //
+        ]);
+        let path = Pathfinder::new(&grid, straight())
+            .find_path(pos(0, 1), &PathGoal::Tile(pos(4, 1)))
+            .unwrap();
+
+        assert_connected(&path, &grid);
+        assert!(path
+            .iter()
+            .all(|tile| tile.y() != 1 || tile.x() == 0 || tile.x() == 4));
+    }
+
+    #[test]
+    fn crosses_heavy_tiles_when_cheaper() {
+        // walking around the wall costs far more than wading through the single heavy tile in its gap
+        let grid = grid(&[
+            "...", //
+            ".#.", //
+            ".2.", //
+            ".#.", //
+        ]);
+        let (path, cost) = Pathfinder::new(&grid, straight())
+            .find_path_within(pos(0, 1), &PathGoal::Tile(pos(2, 1)), |_| true)
+            .unwrap();
+
+        assert_eq!(path, vec![pos(0, 1), pos(1, 1), pos(2, 1)]);
+        assert_eq!(cost, 3 * STRAIGHT_COST);
+    }
+
+    #[test]
+    fn cost_overrides_replace_weights() {
+        let grid = grid(&[
+            "...", //
+            "...", //
+        ]);
+        let overrides = CostOverrides::from_iter([(pos(1, 0), 9.)]);
+        let path = Pathfinder::new(
+            &grid,
+            PathOptions {
+                cost_overrides: Some(Arc::new(overrides)),
+                ..straight()
+            },
+        )
+        .find_path(pos(0, 0), &PathGoal::Tile(pos(2, 0)))
+        .unwrap();
+
+        assert!(!path.contains(&pos(1, 0)));
+    }
+
+    #[test]
+    fn diagonals_never_cut_corners() {
+        let grid = grid(&[
+            ".#", //
+            "..", //
+        ]);
+        let goal = PathGoal::Tile(pos(1, 0));
+        let start = pos(0, 1);
+        let path = Pathfinder::new(&grid, PathOptions::default())
+            .find_path(start, &goal)
+            .unwrap();
+        assert_eq!(path, vec![start, pos(0, 0), pos(1, 0)]);
+    }
+
+    #[test]
+    fn straight_only_takes_no_diagonals() {
+        let grid = grid(&[
+            "...", //
+            "...", //
+            "...", //
+        ]);
+        let path = Pathfinder::new(&grid, straight())
+            .find_path(pos(0, 0), &PathGoal::Tile(pos(2, 2)))
+            .unwrap();
+
+        assert_eq!(path.len(), 5);
+        assert_connected(&path, &grid);
+        assert!(path
+            .windows(2)
+            .all(|step| step[0].x() == step[1].x() || step[0].y() == step[1].y()));
+    }
+
+    #[test]
+    fn gives_up_after_max_nodes() {
+        let grid = grid(&[".........."]);
+        let goal = PathGoal::Tile(pos(9, 0));
+        let limited = |max_nodes| {
+            Pathfinder::new(
+                &grid,
+                PathOptions {
+                    max_nodes: Some(max_nodes),
+                    ..straight()
+                },
+            )
+            .find_path(pos(0, 0), &goal)
+        };
+
+        assert_eq!(limited(3), None);
+        assert!(limited(10).is_some());
+    }
+
+    #[test]
+    fn nearest_reachable_prefers_cheapest_target() {
+        let grid = grid(&[
+            "#.....#", //
+            ".......", //
+        ]);
+        let (target, path) = Pathfinder::new(&grid, straight())
+            .nearest_reachable(pos(4, 1), u32::MAX, |_, tile| !tile.walkable)
+            .unwrap();
+
+        assert_eq!(target, pos(6, 1));
+        assert_eq!(path, vec![pos(4, 1), pos(5, 1), pos(6, 1)]);
+    }
+
+    #[test]
+    fn nearest_reachable_respects_max_cost() {
+        let grid = grid(&["#....."]);
+        let found = Pathfinder::new(&grid, straight()).nearest_reachable(
+            pos(5, 0),
+            4 * STRAIGHT_COST,
+            |_, tile| !tile.walkable,
+        );
+
+        assert_eq!(found, None);
+    }
+
+    #[test]
+    fn line_covers_both_sides_of_corners() {
+        assert_eq!(line_tiles(pos(0, 0), pos(2, 0)), vec![pos(1, 0), pos(2, 0)]);
+        assert_eq!(
+            line_tiles(pos(0, 0), pos(1, 1)),
+            vec![pos(1, 0), pos(0, 1), pos(1, 1)]
+        );
+        assert_eq!(
+            line_tiles(pos(0, 0), pos(2, 1)),
+            vec![pos(1, 0), pos(1, 1), pos(2, 1)]
+        );
+    }
+
+    #[test]
+    fn smooths_across_open_ground() {
+        let grid = grid(&[
+            ".....", //
+            ".....", //
+            ".....", //
+        ]);
+        let pathfinder = Pathfinder::new(&grid, straight());
+        let path = pathfinder
+            .find_path(pos(0, 0), &PathGoal::Tile(pos(4, 2)))
+            .unwrap();
+
+        assert_eq!(pathfinder.smooth_path(path), vec![pos(0, 0), pos(4, 2)]);
+    }
+
+    #[test]
+    fn keeps_waypoints_around_walls() {
+        let grid = grid(&[
+            ".....", //
+            "..#..", //
+            "..#..", //
+        ]);
+        let pathfinder = Pathfinder::new(&grid, PathOptions::default());
+        let path = pathfinder
+            .find_path(pos(0, 0), &PathGoal::Tile(pos(4, 0)))
+            .unwrap();
+        let smoothed = pathfinder.smooth_path(path);
+
+        assert_eq!(smoothed.first(), Some(&pos(0, 0)));
+        assert_eq!(smoothed.last(), Some(&pos(4, 0)));
+        assert!(smoothed.len() > 2);
+        for step in smoothed.windows(2) {
+            assert!(pathfinder.line_is_clear(step[0], step[1], 1.));
+        }
+    }
+
+    #[test]
+    fn never_smooths_through_heavy_tiles() {
+        let grid = grid(&[
+            ".....", //
+            ".999.", //
+            ".....", //
+        ]);
+        let pathfinder = Pathfinder::new(&grid, straight());
+        let path = pathfinder
+            .find_path(pos(0, 1), &PathGoal::Tile(pos(4, 1)))
+            .unwrap();
+        let smoothed = pathfinder.smooth_path(path);
+
+        for step in smoothed.windows(2) {
+            assert!(line_tiles(step[0], step[1]).iter().all(|tile| grid
+                .get(*tile)
+                .unwrap()
+                .weight
+                == 1.));
+        }
+    }
+}
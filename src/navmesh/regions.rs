@@ -0,0 +1,144 @@
+use super::components::NavTileOccupant;
+use crate::{
+    grid::{Grid, NEIGHBORS_4},
+    utils::TilePos,
+};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// Above this many changed tiles it's quicker to relabel the whole map than to patch the regions tile by tile
+const REBUILD_THRESHOLD: usize = 256;
+
+/// The 8 tiles around a tile in clockwise order starting from the one above it, so neighboring entries touch
+const RING: [IVec2; 8] = [
+    IVec2::Y,
+    IVec2::new(1, 1),
+    IVec2::X,
+    IVec2::new(1, -1),
+    IVec2::NEG_Y,
+    IVec2::new(-1, -1),
+    IVec2::NEG_X,
+    IVec2::new(-1, 1),
+];
+
+/// Connected regions of walkable tiles. Two walkable tiles share a region id exactly when a path exists between
+/// them, which turns reachability checks into a lookup instead of a search. Diagonal steps are only allowed when
+/// both tiles beside them are walkable, so connecting tiles through their direct neighbors is enough.
+#[derive(Debug, Default, Clone)]
+pub struct Regions {
+    /// The region of every tile, where `0` means unwalkable
+    ids: Grid<u32>,
+    next_id: u32,
+}
+
+fn is_walkable(tiles: &Grid<NavTileOccupant>, pos: TilePos) -> bool {
+    tiles.get(pos).is_some_and(|tile| tile.walkable)
+}
+
+impl Regions {
+    pub fn region(&self, pos: TilePos) -> Option<u32> {
+        self.ids.get(pos).copied().filter(|&id| id != 0)
+    }
+
+    /// Brings the regions up to date after the walkability of `changed` tiles changed in `tiles`
+    pub fn update(&mut self, tiles: &Grid<NavTileOccupant>, changed: &[TilePos]) {
+        if self.ids.width() != tiles.width()
+            || self.ids.height() != tiles.height()
+            || changed.len() > REBUILD_THRESHOLD
+        {
+            self.rebuild(tiles);
+            return;
+        }
+
+        let (opened, blocked): (Vec<_>, Vec<_>) =
+            changed.iter().partition(|&&pos| is_walkable(tiles, pos));
+
+        for &pos in &blocked {
+            if let Some(id) = self.ids.get_mut(pos) {
+                *id = 0;
+            }
+        }
+
+        // a new walkable tile joins the region of a neighbor, merging any other regions it touches into it
+        for &pos in &opened {
+            let id = tiles
+                .neighbors_4(pos)
+                .find_map(|neighbor| self.region(neighbor))
+                .unwrap_or_else(|| self.fresh_id());
+            self.flood(tiles, pos, id);
+        }
+
+        // a blocked tile may have cut its region in two, so give each side which is still cut off a new id
+        for &pos in &blocked {
+            if !self.may_split(tiles, pos) {
+                continue;
+            }
+
+            let mut relabelled = Vec::new();
+            for neighbor in tiles.neighbors_4(pos) {
+                let Some(id) = self.region(neighbor) else {
+                    continue;
+                };
+                if relabelled.contains(&id) {
+                    continue;
+                }
+
+                let id = self.fresh_id();
+                self.flood(tiles, neighbor, id);
+                relabelled.push(id);
+            }
+        }
+    }
+
+    fn rebuild(&mut self, tiles: &Grid<NavTileOccupant>) {
+        self.ids = Grid::new(tiles.width(), tiles.height());
+        self.next_id = 0;
+
+        for pos in tiles.positions() {
+            if is_walkable(tiles, pos) && self.region(pos).is_none() {
+                let id = self.fresh_id();
+                self.flood(tiles, pos, id);
+            }
+        }
+    }
+
+    fn fresh_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Labels every walkable tile connected to `start` with `id`
+    fn flood(&mut self, tiles: &Grid<NavTileOccupant>, start: TilePos, id: u32) {
+        let mut open = VecDeque::from([start]);
+
+        while let Some(pos) = open.pop_front() {
+            if !is_walkable(tiles, pos) || self.ids.get(pos) == Some(&id) {
+                continue;
+            }
+
+            *self.ids.get_mut(pos).unwrap() = id;
+            open.extend(NEIGHBORS_4.iter().map(|&offset| pos + offset));
+        }
+    }
+
+    /// Whether blocking `pos` might have split its region. If the walkable tiles around it form a single
+    /// unbroken arc they're still connected to each other without it, which avoids relabelling a whole region
+    /// every time a tile in the middle of it is blocked.
+    fn may_split(&self, tiles: &Grid<NavTileOccupant>, pos: TilePos) -> bool {
+        let walkable = RING.map(|offset| is_walkable(tiles, pos + offset));
+
+        // count the arcs of walkable tiles around `pos` which touch it directly, the odd entries are diagonals
+        let arcs = (0..RING.len())
+            .filter(|&i| walkable[i] && !walkable[(i + RING.len() - 1) % RING.len()])
+            .filter(|&i| {
+                (i..i + RING.len())
+                    .map(|j| j % RING.len())
+                    .take_while(|&j| walkable[j])
+                    .any(|j| j % 2 == 0)
+            })
+            .count();
+
+        // when every tile around is walkable there's no arc start, but they're all connected anyway
+        arcs > 1
+    }
+}
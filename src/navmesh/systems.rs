@@ -1,9 +1,18 @@
-use super::components::*;
+use super::{
+    components::*,
+    hierarchy::Hierarchy,
+    pathfinder::{PathOptions, Pathfinder},
+};
+use crate::grid::Grid;
+use crate::pawn::components::Enemy;
 use crate::utils::*;
 use crate::TILE_SIZE;
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool},
+};
 use leafwing_input_manager::prelude::*;
-use pathfinding::prelude::*;
+use std::{cmp::Reverse, collections::BinaryHeap, sync::Arc};
 
 pub fn debug_navmesh(
     navmesh: Res<Navmesh>,
@@ -25,143 +34,246 @@ pub fn debug_navmesh(
 
     let max_weight = 2.;
 
-    for (x, row) in navmesh.0.iter().enumerate() {
-        for (y, tile) in row.iter().enumerate() {
-            let tile_position = Vec2::new(x as f32, y as f32).tile_pos_to_world()
-                + Vec2::new(TILE_SIZE / 2., TILE_SIZE / 2.);
-
-            if !tile.walkable {
-                gizmos.rect_2d(
-                    tile_position,
-                    0.,
-                    Vec2::new(TILE_SIZE, TILE_SIZE),
-                    Color::RED,
-                );
-            } else {
-                let weight_color = Color::rgb(
-                    tile.weight / max_weight,
-                    tile.weight / max_weight,
-                    tile.weight / max_weight,
-                );
-                gizmos.rect_2d(
-                    tile_position,
-                    0.,
-                    Vec2::new(TILE_SIZE, TILE_SIZE),
-                    weight_color,
-                );
-            }
+    for (pos, tile) in navmesh.tiles().iter() {
+        let tile_position = pos.center().0;
+
+        if !tile.walkable {
+            gizmos.rect_2d(
+                tile_position,
+                0.,
+                Vec2::new(TILE_SIZE, TILE_SIZE),
+                Color::RED,
+            );
+        } else {
+            let weight_color = Color::rgb(
+                tile.weight / max_weight,
+                tile.weight / max_weight,
+                tile.weight / max_weight,
+            );
+            gizmos.rect_2d(
+                tile_position,
+                0.,
+                Vec2::new(TILE_SIZE, TILE_SIZE),
+                weight_color,
+            );
         }
     }
 }
 
-pub fn get_pathing(request: PathfindRequest, navmesh: &Res<Navmesh>) -> Option<Vec<Vec2>> {
-    let Vec2 { x, y } = request.start;
-    let start_x = x as usize;
-    let start_y = y as usize;
-
-    let Vec2 { x: end_x, y: end_y } = request.end;
-    let end_x = end_x as usize;
-    let end_y = end_y as usize;
-
-    let result = astar(
-        &(start_x, start_y),
-        |&(x, y)| {
-            let up = (x, y.saturating_add(1));
-            let down = (x, y.saturating_sub(1));
-            let left = (x.saturating_sub(1), y);
-            let right = (x.saturating_add(1), y);
-
-            let neighbors = [up, down, left, right]
-                .iter()
-                .filter(|&(x, y)| {
-                    navmesh
-                        .0
-                        .get(*x)
-                        .and_then(|row| row.get(*y))
-                        .map(|tile| {
-                            tile.walkable
-                                || (*x == end_x && *y == end_y)
-                                || (*x == start_x && *y == start_y)
-                        })
-                        .unwrap_or(false)
-                })
-                .map(|(x, y)| ((*x, *y), 0)) // Modify this line
-                .collect::<Vec<_>>();
-
-            neighbors
-        },
-        |&(x, y)| {
-            (Vec2::new(x as f32, y as f32) - Vec2::new(end_x as f32, end_y as f32)).length() as i32
-        },
-        |(x, y)| x == &end_x && y == &end_y,
-    )
-    .map(|(data, _)| {
-        data.iter()
-            .map(|item| Vec2::new(item.0 as f32, item.1 as f32))
-            .collect::<Vec<_>>()
-    });
-
-    result
+pub fn update_navmesh_snapshot(navmesh: Res<Navmesh>, mut snapshot: ResMut<NavmeshSnapshot>) {
+    let tiles = navmesh.tiles();
+
+    snapshot.0 = Arc::new(Grid::from_fn(tiles.width(), tiles.height(), |pos| {
+        let tile = tiles.get(pos).unwrap();
+        NavCell {
+            walkable: tile.walkable,
+            weight: tile.weight,
+        }
+    }));
+}
+
+/// Updates the connected regions and sends the tiles changed through the [`Navmesh`] API since the last frame
+pub fn send_navmesh_changes(
+    mut navmesh: ResMut<Navmesh>,
+    mut navmesh_changed: EventWriter<NavmeshChanged>,
+) {
+    // updating the regions isn't a change to the walkability of the navmesh itself
+    let tiles = navmesh.bypass_change_detection().flush_changes();
+
+    if !tiles.is_empty() {
+        navmesh_changed.send(NavmeshChanged { tiles });
+    }
+}
+
+/// Keeps the cluster graph in sync with the navmesh snapshot, rebuilding only the clusters around changed tiles,
+/// or everything when the map or the pathfinding settings were replaced
+pub fn update_navmesh_hierarchy(
+    snapshot: Res<NavmeshSnapshot>,
+    settings: Res<PathfindingSettings>,
+    mut hierarchy: ResMut<NavmeshHierarchy>,
+    mut navmesh_changed: EventReader<NavmeshChanged>,
+) {
+    let NavmeshHierarchy { graph, size } = &mut *hierarchy;
+    let navmesh = &snapshot.0;
+    let changed = navmesh_changed
+        .read()
+        .flat_map(|event| event.tiles.iter().copied())
+        .collect::<Vec<_>>();
+
+    if *size != (navmesh.width(), navmesh.height())
+        || graph.allow_diagonals() != settings.allow_diagonals
+    {
+        *graph = Arc::new(Hierarchy::build(navmesh, settings.allow_diagonals));
+        *size = (navmesh.width(), navmesh.height());
+    } else if !changed.is_empty() {
+        // tasks still solving against the old graph keep their copy
+        Arc::make_mut(graph).update(navmesh, changed);
+    }
 }
 
+/// Queues new requests and then drops the cancelled ones. A cancellation only names a single request, so it never
+/// drops a newer request of the same entity.
 pub fn listen_for_pathfinding_requests(
     mut pathfinding_event_reader: EventReader<PathfindRequest>,
-    navmesh: Res<Navmesh>,
-    mut pathfinding_event_writer: EventWriter<PathfindAnswer>,
+    mut cancel_event_reader: EventReader<CancelPathfinding>,
+    mut queue: ResMut<PathfindingQueue>,
+    q_enemies: Query<(), With<Enemy>>,
 ) {
-    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    struct UsizeVec {
-        x: usize,
-        y: usize,
-    }
-    let navmesh = &navmesh.0;
     for request in pathfinding_event_reader.read() {
-        let Vec2 { x, y } = request.start;
-        let x = x as usize;
-        let y = y as usize;
-
-        let Vec2 { x: end_x, y: end_y } = request.end;
-        let end_x = end_x as usize;
-        let end_y = end_y as usize;
-
-        let result = astar(
-            &UsizeVec { x, y },
-            |&UsizeVec { x, y }| {
-                let up = (x, y.saturating_add(1));
-                let down = (x, y.saturating_sub(1));
-                let left = (x.saturating_sub(1), y);
-                let right = (x.saturating_add(1), y);
-
-                let neighbors = [up, down, left, right]
-                    .iter()
-                    .filter(|&(x, y)| {
-                        navmesh
-                            .get(*x)
-                            .and_then(|row| row.get(*y))
-                            .map(|tile| tile.walkable || (*x == end_x && *y == end_y))
-                            .unwrap_or(false)
-                    })
-                    .map(|(x, y)| (UsizeVec { x: *x, y: *y }, 0)) // Modify this line
-                    .collect::<Vec<_>>();
-
-                neighbors
-            },
-            |&tile| {
-                (Vec2::new(tile.x as f32, tile.y as f32) - Vec2::new(end_x as f32, end_y as f32))
-                    .length() as i32
-            },
-            |UsizeVec { x, y }| x == &end_x && y == &end_y,
-        )
-        .map(|(data, _)| {
-            data.iter()
-                .map(|item| Vec2::new(item.x as f32, item.y as f32))
-                .collect::<Vec<_>>()
-        });
+        let faction = if q_enemies.contains(request.entity) {
+            Faction::Enemy
+        } else {
+            Faction::Colony
+        };
+        queue.push(*request, faction);
+    }
+
+    for CancelPathfinding { entity, id } in cancel_event_reader.read() {
+        queue.cancel(*entity, *id);
+    }
+}
 
-        pathfinding_event_writer.send(PathfindAnswer {
-            path: result,
-            entity: request.entity,
-            target: request.end,
+/// Hands up to `max_solves_per_frame` queued requests to the async compute pool, the most urgent ones first
+pub fn dispatch_pathfinding_tasks(
+    mut queue: ResMut<PathfindingQueue>,
+    snapshot: Res<NavmeshSnapshot>,
+    hierarchy: Res<NavmeshHierarchy>,
+    settings: Res<PathfindingSettings>,
+) {
+    let task_pool = AsyncComputeTaskPool::get();
+    let mut dispatched = 0;
+
+    while dispatched < settings.max_solves_per_frame {
+        let Some(QueuedRequest { request, faction }) = queue.pending.pop() else {
+            break;
+        };
+
+        // a newer request from the same entity is already queued, or it was cancelled, so don't solve this one
+        if !queue.is_latest(request.entity, request.id) {
+            continue;
+        }
+
+        let PathfindRequest {
+            start,
+            goal,
+            smooth,
+            ..
+        } = request;
+        let navmesh = snapshot.0.clone();
+        let graph = hierarchy.graph.clone();
+        let options = settings.options(faction);
+
+        let task = task_pool.spawn(async move {
+            let pathfinder = Pathfinder::new(&navmesh, options);
+            let path = graph.find_path(&pathfinder, start, &goal)?;
+            Some(if smooth {
+                pathfinder.smooth_path(path)
+            } else {
+                path
+            })
         });
+
+        queue.in_flight.push(PathfindTask { request, task });
+        dispatched += 1;
+    }
+}
+
+/// Sends a [`PathfindAnswer`] for every finished task whose request hasn't been superseded
+pub fn poll_pathfinding_tasks(
+    mut queue: ResMut<PathfindingQueue>,
+    mut pathfinding_event_writer: EventWriter<PathfindAnswer>,
+) {
+    let (finished, running) = std::mem::take(&mut queue.in_flight)
+        .into_iter()
+        .partition::<Vec<_>, _>(|task| task.task.is_finished());
+    queue.in_flight = running;
+
+    for PathfindTask { request, task } in finished {
+        let path = block_on(task);
+
+        if !queue.is_latest(request.entity, request.id) {
+            continue;
+        }
+        queue.complete(request.entity, request.id);
+
+        pathfinding_event_writer.send(PathfindAnswer { request, path });
+    }
+}
+
+/// Runs Dijkstra outward from every goal at once, producing each tile's cost to the nearest goal and the neighbor
+/// to step onto to follow the cheapest route there. Goals which aren't walkable right now, like a tree growing
+/// next to the factory, are left out so nothing is led onto them.
+fn integrate_flow_field<T: NavTile>(
+    navmesh: &Grid<T>,
+    goals: &[TilePos],
+    options: PathOptions,
+) -> FlowFieldLayers {
+    let pathfinder = Pathfinder::new(navmesh, options);
+    let mut costs = Grid::filled(navmesh.width(), navmesh.height(), u32::MAX);
+    let mut next = Grid::new(navmesh.width(), navmesh.height());
+    let mut open = BinaryHeap::new();
+
+    for &goal in goals.iter().filter(|&&goal| pathfinder.is_walkable(goal)) {
+        if let Some(cost) = costs.get_mut(goal) {
+            *cost = 0;
+            open.push(Reverse((0, goal.x(), goal.y())));
+        }
+    }
+
+    while let Some(Reverse((cost, x, y))) = open.pop() {
+        let pos = TilePos::new(x, y);
+        if costs.get(pos).is_some_and(|&best| cost > best) {
+            continue;
+        }
+
+        // the cost to walk from a neighbor onto `pos`, which is the reverse of the step `successors` yields
+        for (neighbor, _) in pathfinder.successors(pos, |neighbor| pathfinder.is_walkable(neighbor))
+        {
+            let neighbor_cost = cost + pathfinder.step_cost(neighbor, pos);
+
+            if costs
+                .get(neighbor)
+                .is_some_and(|&best| neighbor_cost < best)
+            {
+                *costs.get_mut(neighbor).unwrap() = neighbor_cost;
+                *next.get_mut(neighbor).unwrap() = Some(pos);
+                open.push(Reverse((neighbor_cost, neighbor.x(), neighbor.y())));
+            }
+        }
+    }
+
+    (costs, next)
+}
+
+/// Starts recomputing the flow field on the async compute pool when its goals or the navmesh change, and swaps
+/// the result in once it's done. Until then entities keep following the previous field, except onto tiles which
+/// have been blocked since.
+pub fn update_flow_field(
+    mut flow_field: ResMut<FlowField>,
+    snapshot: Res<NavmeshSnapshot>,
+    settings: Res<PathfindingSettings>,
+) {
+    if (flow_field.dirty || snapshot.is_changed()) && !flow_field.goals().is_empty() {
+        let navmesh = snapshot.0.clone();
+        let goals = flow_field.goals().to_vec();
+        // only enemies follow the flow field
+        let options = settings.options(Faction::Enemy);
+
+        // replacing a running task drops it, which cancels the outdated computation
+        flow_field.task = Some(
+            AsyncComputeTaskPool::get()
+                .spawn(async move { integrate_flow_field(&navmesh, &goals, options) }),
+        );
+        flow_field.dirty = false;
+    }
+
+    if flow_field
+        .task
+        .as_ref()
+        .is_some_and(|task| task.is_finished())
+    {
+        let (costs, next) = block_on(flow_field.task.take().unwrap());
+        flow_field.costs = costs;
+        flow_field.next = next;
     }
 }
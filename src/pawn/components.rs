@@ -1,4 +1,7 @@
-use crate::assets::CharacterFacing;
+use crate::{
+    assets::CharacterFacing, economy::ResourceStore, navmesh::components::PathfindRequest,
+    utils::TilePos,
+};
 use bevy::prelude::*;
 pub use pawn_status::ClearStatus;
 use std::collections::VecDeque;
@@ -6,8 +9,8 @@ pub use work_order::ClearWorkOrder;
 
 #[derive(Component, Reflect)]
 pub struct Pawn {
-    pub move_path: VecDeque<Vec2>,
-    pub move_to: Option<Vec2>,
+    pub move_path: VecDeque<TilePos>,
+    pub move_to: Option<TilePos>,
     pub health: usize,
     pub max_health: usize,
     pub animation_timer: Timer,
@@ -15,6 +18,9 @@ pub struct Pawn {
     pub search_timer: Timer,
     pub retry_pathfinding_timer: Timer,
     pub moving: bool,
+    /// The request `move_path` was found for, so the pawn can ask for the same goal again if its path gets blocked
+    #[reflect(ignore)]
+    pub path_request: Option<PathfindRequest>,
 }
 
 #[derive(Component)]
@@ -39,17 +45,32 @@ pub struct PawnBundle<T: Component + pawn_status::Status> {
     pub resources: CarriedResources,
 }
 
-#[derive(Component)]
-pub struct CarriedResources(pub usize);
+#[derive(Component, Default, Deref, DerefMut)]
+pub struct CarriedResources(pub ResourceStore);
 
 pub mod pawn_status {
+    use crate::navmesh::components::PathRequestId;
     use bevy::{ecs::system::EntityCommands, prelude::*};
 
+    macro_rules! status_struct {
+        ($name:ident) => {
+            #[derive(Component)]
+            pub struct $name;
+        };
+        ($name:ident { $($field:ident: $ty:ty),* }) => {
+            #[derive(Component)]
+            pub struct $name {
+                $(
+                    pub $field: $ty
+                ),*
+            }
+        };
+    }
+
     macro_rules! pawn_status {
-        ($($name:ident),*) => {
+        ($($name:ident $({ $($field:ident: $ty:ty),* $(,)? })?),*) => {
             $(
-                #[derive(Component)]
-                pub struct $name;
+                status_struct!($name $({ $($field: $ty),* })?);
                 impl Status for $name {}
             )*
 
@@ -85,10 +106,14 @@ pub mod pawn_status {
 
     pawn_status!(
         Idle,
-        Pathfinding,
+        // waiting for the answer to the path request with this ID
+        Pathfinding {
+            request: PathRequestId
+        },
         PathfindingError,
         Moving,
         Mining,
+        Chopping,
         Attacking
     );
 }
@@ -166,6 +191,9 @@ pub mod work_order {
         struct MineStone {
             stone_entity: Entity,
         },
+        struct ChopTree {
+            tree_entity: Entity,
+        },
         struct ReturnToFactory {},
         struct BuildItem {
             item_entity: Entity,
@@ -43,6 +43,7 @@ impl Plugin for PawnPlugin {
                 Update,
                 (
                     systems::mine_stone,
+                    systems::chop_tree,
                     systems::work_idle_pawns,
                     systems::return_to_factory,
                 )
@@ -63,14 +64,20 @@ impl Plugin for PawnPlugin {
             .add_systems(
                 Update,
                 (
+                    systems::invalidate_blocked_paths,
                     systems::retry_pathfinding,
                     systems::enemy_search_for_factory,
                     systems::listen_for_pathfinding_answers,
+                    systems::follow_flow_field,
                     systems::move_pawn,
                 )
                     .chain()
                     .in_set(PawnSystemSet::Move),
             )
+            .add_systems(
+                Update,
+                systems::cancel_abandoned_pathfinding.in_set(PawnSystemSet::Last),
+            )
             // add general systems
             .add_systems(
                 Update,
@@ -1,17 +1,29 @@
 use super::components::pawn_status::PawnStatus;
-use super::components::work_order::{AddWorkOrder, MineStone, WorkOrder};
+use super::components::work_order::{AddWorkOrder, ChopTree, MineStone, WorkOrder};
 use super::{EnemyWave, SpawnPawnRequestEvent};
-use crate::factory::components::{Factory, Placed};
-use crate::navmesh::components::{NavTileOccupant, Navmesh, PathfindAnswer, PathfindRequest};
-use crate::navmesh::get_pathing;
+use crate::factory::{
+    components::{Factory, Placed},
+    factory_bounds,
+};
+use crate::fog::{Vision, PAWN_VISION_RADIUS};
+use crate::navmesh::components::{
+    CancelPathfinding, Faction, FlowField, NavTileOccupant, Navmesh, NavmeshChanged, PathGoal,
+    PathPriority, PathRequestId, PathfindAnswer, PathfindRequest, PathfindingSettings,
+};
+use crate::navmesh::pathfinder::{line_tiles, STRAIGHT_COST};
 use crate::pawn::components::pawn_status::AddStatus;
 use crate::stone::{Stone, StoneKind};
+use crate::tree::{Tree, TreeKind};
 use crate::{
     assets::{CharacterFacing, MalePawns},
     pawn::components::*,
     utils::*,
 };
-use crate::{CursorPosition, GameResources, GameState, SIZE, TILE_SIZE};
+use crate::{
+    economy::{ResourceKind, ResourceLedger, PAWN_COST},
+    rng::GameRng,
+    CursorPosition, GameResources, GameState, TILE_SIZE,
+};
 use bevy::ecs::query::ReadOnlyWorldQuery;
 use bevy::prelude::*;
 use bevy::utils::{HashMap, HashSet};
@@ -23,11 +35,26 @@ const INITIAL_PAWN_COUNT: usize = 10;
 const MOVE_SPEED: f32 = 60.;
 const MAX_RESOURCES: usize = 15;
 const RESOURCE_GAIN_RATE: usize = 1;
-const PAWN_COST: usize = 100;
 const PAWN_ATTACK_STRENGTH: usize = 5;
 const ENEMY_TILE_RANGE: usize = 10;
 const ENEMY_ATTACK_STRENGTH: usize = 10;
 const PAWN_SEARCH_TIMER: f32 = 0.25;
+/// How far a pawn looks for stones and trees to harvest, about 100 tiles of dirt
+const RESOURCE_SEARCH_COST: u32 = 100 * STRAIGHT_COST;
+
+/// Anywhere around the outside of the factory, so pawns don't all queue up at the same tile
+fn factory_goal(factory: TilePos) -> PathGoal {
+    let (min, max) = factory_bounds(factory);
+    PathGoal::Around { min, max }
+}
+
+/// Close enough to a pawn to attack it
+fn attack_goal(target: TilePos) -> PathGoal {
+    PathGoal::Near {
+        center: target,
+        radius: 1,
+    }
+}
 
 fn spawn_pawn_in_random_location(
     commands: &mut Commands,
@@ -35,11 +62,11 @@ fn spawn_pawn_in_random_location(
     game_resources: &mut ResMut<GameResources>,
     factory_transform: &GlobalTransform,
     _: &Res<Navmesh>,
+    rng: &mut impl Rng,
 ) {
     let radius = TILE_SIZE * 5.;
-    let mut rng = rand::thread_rng();
 
-    let pawn = pawn_res.get_random();
+    let pawn = pawn_res.get_random(rng);
 
     // spawn pawns in a random circle 1 tile around the factory
     let random_angle: f32 = rng.gen_range(0.0..360.0);
@@ -47,33 +74,39 @@ fn spawn_pawn_in_random_location(
     let y = factory_transform.translation().y + random_angle.sin() * radius;
 
     let pawn_entity = commands
-        .spawn((PawnBundle {
-            pawn: Pawn {
-                move_path: VecDeque::new(),
-                move_to: None,
-                health: 100,
-                max_health: 100,
-                animation_timer: Timer::from_seconds(0.125, TimerMode::Repeating),
-                mine_timer: Timer::from_seconds(0.5, TimerMode::Once),
-                moving: false,
-                search_timer: Timer::from_seconds(PAWN_SEARCH_TIMER, TimerMode::Repeating),
-                retry_pathfinding_timer: Timer::from_seconds(1., TimerMode::Once),
-            },
-            character_facing: CharacterFacing::Left,
-            name: Name::new("Pawn"),
-            sprite_bundle: SpriteSheetBundle {
-                texture_atlas: pawn,
-                transform: Transform::from_translation(Vec3::new(x, y, 1.)),
-                sprite: TextureAtlasSprite {
-                    anchor: bevy::sprite::Anchor::BottomLeft,
-                    index: CharacterFacing::Left as usize,
-                    ..default()
+        .spawn((
+            PawnBundle {
+                pawn: Pawn {
+                    move_path: VecDeque::new(),
+                    move_to: None,
+                    health: 100,
+                    max_health: 100,
+                    animation_timer: Timer::from_seconds(0.125, TimerMode::Repeating),
+                    mine_timer: Timer::from_seconds(0.5, TimerMode::Once),
+                    moving: false,
+                    search_timer: Timer::from_seconds(PAWN_SEARCH_TIMER, TimerMode::Repeating),
+                    retry_pathfinding_timer: Timer::from_seconds(1., TimerMode::Once),
+                    path_request: None,
+                },
+                character_facing: CharacterFacing::Left,
+                name: Name::new("Pawn"),
+                sprite_bundle: SpriteSheetBundle {
+                    texture_atlas: pawn,
+                    transform: Transform::from_translation(Vec3::new(x, y, 1.)),
+                    sprite: TextureAtlasSprite {
+                        anchor: bevy::sprite::Anchor::BottomLeft,
+                        index: CharacterFacing::Left as usize,
+                        ..default()
+                    },
+                    ..Default::default()
                 },
-                ..Default::default()
+                pawn_status: PawnStatus(Box::new(pawn_status::Idle)),
+                resources: CarriedResources::default(),
             },
-            pawn_status: PawnStatus(Box::new(pawn_status::Idle)),
-            resources: CarriedResources(0),
-        },))
+            Vision {
+                radius: PAWN_VISION_RADIUS,
+            },
+        ))
         .id();
 
     commands
@@ -101,6 +134,7 @@ pub fn initial_pawn_spawn(
     mut game_resources: ResMut<GameResources>,
     navmesh: Res<Navmesh>,
     mut next_state: ResMut<NextState<GameState>>,
+    mut game_rng: ResMut<GameRng>,
 ) {
     let Ok(factory_transform) = q_factory.get_single() else {
         return;
@@ -113,20 +147,22 @@ pub fn initial_pawn_spawn(
             &mut game_resources,
             factory_transform,
             &navmesh,
+            &mut game_rng.pawns,
         );
     }
 
     next_state.set(GameState::Main);
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn work_idle_pawns(
     mut commands: Commands,
     mut q_pawns: Query<
-        (Entity, &Transform, &CarriedResources),
+        (Entity, &Transform, &CarriedResources, &mut Pawn),
         (
-            With<Pawn>,
             Without<WorkOrder<work_order::ReturnToFactory>>,
             Without<WorkOrder<work_order::MineStone>>,
+            Without<WorkOrder<work_order::ChopTree>>,
             Without<PawnStatus<pawn_status::Moving>>,
             Without<WorkOrder<work_order::AttackPawn>>,
             With<PawnStatus<pawn_status::Idle>>,
@@ -134,97 +170,88 @@ pub fn work_idle_pawns(
         ),
     >,
     q_stones: Query<Entity, With<StoneKind>>,
+    q_trees: Query<Entity, With<TreeKind>>,
     q_factory: Query<&GlobalTransform, (With<Factory>, With<Placed>)>,
     navmesh: Res<Navmesh>,
+    pathfinding_settings: Res<PathfindingSettings>,
     mut pathfinding_event_writer: EventWriter<PathfindRequest>,
 ) {
-    let navmesh_tiles = &navmesh.0;
     let Ok(factory_transform) = q_factory.get_single() else {
         return;
     };
 
-    fn check_for_stones(
-        entity_set: &HashSet<Entity>,
-        q_stones: &Query<Entity, With<StoneKind>>,
-    ) -> (bool, Option<Entity>) {
-        for entity in entity_set.iter() {
+    enum Harvestable {
+        Stone(Entity),
+        Tree(Entity),
+    }
+
+    let check_for_resources = |entity_set: &HashSet<Entity>| -> Option<Harvestable> {
+        entity_set.iter().find_map(|entity| {
             if q_stones.get(*entity).is_ok() {
-                return (true, Some(*entity));
+                Some(Harvestable::Stone(*entity))
+            } else if q_trees.get(*entity).is_ok() {
+                Some(Harvestable::Tree(*entity))
+            } else {
+                None
             }
-        }
-        (false, None)
-    }
+        })
+    };
 
-    for (entity, transform, resources) in &mut q_pawns {
+    for (entity, transform, resources, mut pawn) in &mut q_pawns {
         // check if the pawn is full on resources
-        if resources.0 >= MAX_RESOURCES {
+        if resources.total() >= MAX_RESOURCES {
+            let request = PathfindRequest::new(
+                transform.tile_pos(),
+                factory_goal(factory_transform.tile_pos()),
+                entity,
+            )
+            .smoothed();
+
             commands
                 .entity(entity)
-                .add_status(pawn_status::Pathfinding)
+                .add_status(pawn_status::Pathfinding {
+                    request: request.id,
+                })
                 .add_work_order(work_order::ReturnToFactory {});
 
-            let grid_location = transform.translation.world_pos_to_tile();
-            let factory_grid = factory_transform.translation().world_pos_to_tile();
+            pathfinding_event_writer.send(request);
 
-            pathfinding_event_writer.send(PathfindRequest {
-                start: grid_location,
-                end: factory_grid,
-                entity,
-            });
+            continue;
+        }
 
+        // the search floods the navmesh on the main thread, so idle pawns only run it when their search timer fires
+        if !pawn.search_timer.finished() {
             continue;
         }
 
-        let grid_location = transform.translation.world_pos_to_tile();
-
-        let grid_x = grid_location.x as usize;
-        let grid_y = grid_location.y as usize;
-
-        // search the navmesh for non-walkable tiles, and see if the entities within are in q_stones
-        let stone_location;
-        let stone_entity;
-        let mut search_radius: usize = 1;
-
-        // Find the closest stone to the pawn ensuring that the pawn can reach the stone by pathfinding
-        'base: loop {
-            for x in (grid_x.saturating_sub(search_radius))..=(grid_x + search_radius) {
-                for y in (grid_y.saturating_sub(search_radius))..=(grid_y + search_radius) {
-                    if let Some(tile) = navmesh_tiles.get(x).and_then(|row| row.get(y)) {
-                        let (found, stone_ent) = check_for_stones(&tile.occupied_by, &q_stones);
-
-                        if !tile.walkable
-                            && found
-                            && get_pathing(
-                                PathfindRequest {
-                                    start: Vec2::new(x as f32, y as f32),
-                                    end: Vec2::new(grid_x as f32, grid_y as f32),
-                                    entity,
-                                },
-                                &navmesh,
-                            )
-                            .is_some()
-                        {
-                            stone_entity = stone_ent;
-                            stone_location = Some(Vec2::new(x as f32, y as f32));
-                            break 'base;
-                        }
-                    }
-                }
+        // find the stone or tree which is the shortest walk away, the search finds the path to it as well
+        let pathfinder = navmesh.pathfinder(pathfinding_settings.options(Faction::Colony));
+        let Some((resource_pos, resource, path)) = pathfinder
+            .nearest_reachable(transform.tile_pos(), RESOURCE_SEARCH_COST, |_, tile| {
+                check_for_resources(&tile.occupied_by).is_some()
+            })
+            .and_then(|(pos, path)| {
+                let resource = check_for_resources(&navmesh.get(pos)?.occupied_by)?;
+                Some((pos, resource, path))
+            })
+        else {
+            continue;
+        };
+
+        pawn.move_path = pathfinder.smooth_path(path).into();
+        pawn.path_request = Some(
+            PathfindRequest::new(transform.tile_pos(), PathGoal::Tile(resource_pos), entity)
+                .smoothed(),
+        );
+        let mut pawn_commands = commands.entity(entity);
+        pawn_commands.add_status(pawn_status::Moving);
+        match resource {
+            Harvestable::Stone(stone_entity) => {
+                pawn_commands.add_work_order(MineStone { stone_entity });
+            }
+            Harvestable::Tree(tree_entity) => {
+                pawn_commands.add_work_order(ChopTree { tree_entity });
             }
-            search_radius += 1;
-        }
-        if let Some(stone_location) = stone_location {
-            commands
-                .entity(entity)
-                .add_status(pawn_status::Pathfinding)
-                .add_work_order(MineStone {
-                    stone_entity: stone_entity.unwrap(),
-                });
-            pathfinding_event_writer.send(PathfindRequest {
-                start: grid_location,
-                end: stone_location,
-                entity,
-            });
         }
     }
 }
@@ -232,25 +259,122 @@ pub fn work_idle_pawns(
 pub fn listen_for_pathfinding_answers(
     mut commands: Commands,
     mut answer_events: EventReader<PathfindAnswer>,
-    mut q_pawns: Query<&mut Pawn, (With<Pawn>, With<PawnStatus<pawn_status::Pathfinding>>)>,
+    mut q_pawns: Query<(&mut Pawn, &PawnStatus<pawn_status::Pathfinding>)>,
 ) {
-    for evt in answer_events.read() {
-        let Ok(mut pawn) = q_pawns.get_mut(evt.entity) else {
+    for PathfindAnswer { request, path } in answer_events.read() {
+        let Ok((mut pawn, PawnStatus(status))) = q_pawns.get_mut(request.entity) else {
             continue;
         };
 
-        if let Some(path) = &evt.path {
+        // the pawn has made a newer request since this one
+        if status.request != request.id {
+            continue;
+        }
+
+        if let Some(path) = path {
             pawn.move_path = path.clone().into();
-            commands.entity(evt.entity).add_status(pawn_status::Moving);
+            pawn.path_request = Some(*request);
+            commands
+                .entity(request.entity)
+                .add_status(pawn_status::Moving);
         } else {
             commands
-                .entity(evt.entity)
+                .entity(request.entity)
                 .clear_work_order()
                 .add_status(pawn_status::PathfindingError);
         }
     }
 }
 
+/// Re-requests the path of every pawn whose remaining path walks through a tile which has just been blocked.
+/// The last tile of a path is its target and is allowed to be unwalkable, so only the tiles leading up to it count.
+pub fn invalidate_blocked_paths(
+    mut commands: Commands,
+    mut navmesh_changed: EventReader<NavmeshChanged>,
+    mut q_pawns: Query<(Entity, &mut Pawn, &Transform)>,
+    navmesh: Res<Navmesh>,
+    mut pathfinding_event_writer: EventWriter<PathfindRequest>,
+) {
+    let blocked = navmesh_changed
+        .read()
+        .flat_map(|event| event.tiles.iter().copied())
+        .filter(|&pos| navmesh.get(pos).is_some_and(|tile| !tile.walkable))
+        .collect::<HashSet<_>>();
+
+    if blocked.is_empty() {
+        return;
+    }
+
+    for (entity, mut pawn, transform) in &mut q_pawns {
+        let remaining = pawn
+            .move_to
+            .iter()
+            .chain(pawn.move_path.iter())
+            .copied()
+            .collect::<Vec<_>>();
+        let Some(&target) = remaining.last() else {
+            continue;
+        };
+
+        // smoothed paths walk straight between waypoints, so check every tile along the way
+        let mut from = transform.tile_pos();
+        let crosses_blocked = remaining.iter().any(|&to| {
+            let crosses = line_tiles(from, to)
+                .into_iter()
+                .any(|pos| pos != target && blocked.contains(&pos));
+            from = to;
+            crosses
+        });
+        if !crosses_blocked {
+            continue;
+        }
+
+        // stop on the current tile until the new path arrives
+        pawn.move_path.clear();
+        pawn.move_to = None;
+
+        // flow field followers pick their next step on their own
+        let Some(request) = pawn.path_request.take() else {
+            continue;
+        };
+
+        let request = request.retry_from(transform.tile_pos());
+        pathfinding_event_writer.send(request);
+        commands
+            .entity(entity)
+            .add_status(pawn_status::Pathfinding {
+                request: request.id,
+            });
+    }
+}
+
+/// Cancels the path requests of pawns which stopped waiting for an answer, because they gave up on their work order,
+/// got a path some other way or despawned
+pub fn cancel_abandoned_pathfinding(
+    mut removed_pathfinding: RemovedComponents<PawnStatus<pawn_status::Pathfinding>>,
+    q_pathfinding: Query<(Entity, &PawnStatus<pawn_status::Pathfinding>)>,
+    mut cancel_event_writer: EventWriter<CancelPathfinding>,
+    mut requests: Local<HashMap<Entity, PathRequestId>>,
+) {
+    for entity in removed_pathfinding.read() {
+        // switching to a newer request removes the status before inserting it again
+        if q_pathfinding.contains(entity) {
+            continue;
+        }
+        if let Some(id) = requests.remove(&entity) {
+            cancel_event_writer.send(CancelPathfinding { entity, id });
+        }
+    }
+
+    // the status is gone by the time it shows up as removed, so remember which request each pawn waits on
+    requests.clear();
+    requests.extend(
+        q_pathfinding
+            .iter()
+            .map(|(entity, PawnStatus(status))| (entity, status.request)),
+    );
+}
+
 pub fn move_pawn(
     mut commands: Commands,
     mut q_pawn: ParamSet<(
@@ -270,8 +394,6 @@ pub fn move_pawn(
     time: Res<Time>,
 ) {
     for (mut transform, mut pawn, mut facing) in &mut q_pawn.p1() {
-        let current_grid = transform.translation.world_pos_to_tile();
-
         if pawn.move_to.is_none() {
             pawn.move_to = pawn.move_path.pop_front();
         }
@@ -281,9 +403,10 @@ pub fn move_pawn(
             continue;
         };
 
-        let direction = (path - current_grid).normalize_or_zero();
+        let to_target = path.corner().0 - transform.world_pos().0;
+        let direction = to_target.normalize_or_zero();
+        let step = MOVE_SPEED * time.delta_seconds();
 
-        transform.translation += direction.extend(0.) * MOVE_SPEED * time.delta_seconds();
         pawn.moving = true;
         // update facing direction depending on direction (right, left, forward, backwards)
 
@@ -300,8 +423,13 @@ pub fn move_pawn(
                 *facing = CharacterFacing::Forward;
             }
         }
-        if (path - current_grid).length() < 0.2 {
+
+        // snap onto the tile once it's within reach this frame so we never overshoot it
+        if to_target.length() <= step {
+            transform.translation = path.corner().extend(transform.translation.z);
             pawn.move_to = pawn.move_path.pop_front();
+        } else {
+            transform.translation += direction.extend(0.) * step;
         }
     }
 
@@ -395,7 +523,7 @@ pub fn mine_stone(
             Without<PawnStatus<pawn_status::Moving>>,
         ),
     >,
-    mut q_stones: Query<(Entity, &mut Stone, &Transform), With<StoneKind>>,
+    mut q_stones: Query<(Entity, &mut Stone, &StoneKind, &Transform)>,
     mut navmesh: ResMut<Navmesh>,
 ) {
     let mut destroyed_stones = HashSet::<Entity>::default();
@@ -408,7 +536,7 @@ pub fn mine_stone(
     }
 
     for (pawn_entity, pawn, mut carried_resources, work_order) in &mut q_pawns {
-        if carried_resources.0 >= MAX_RESOURCES {
+        if carried_resources.total() >= MAX_RESOURCES {
             commands
                 .entity(pawn_entity)
                 .add_status(pawn_status::Idle)
@@ -418,7 +546,7 @@ pub fn mine_stone(
         }
 
         if pawn.mine_timer.finished() {
-            let Ok((stone_entity, mut stone, stone_transform)) =
+            let Ok((stone_entity, mut stone, stone_kind, stone_transform)) =
                 q_stones.get_mut(work_order.0.stone_entity)
             else {
                 commands
@@ -431,7 +559,7 @@ pub fn mine_stone(
             if stone.remaining_resources > 0 {
                 stone.remaining_resources =
                     stone.remaining_resources.saturating_sub(RESOURCE_GAIN_RATE);
-                carried_resources.0 = carried_resources.0.saturating_add(RESOURCE_GAIN_RATE);
+                carried_resources.add(ResourceKind::from(*stone_kind), RESOURCE_GAIN_RATE);
             } else {
                 // we're about to despawn an entity, get it's grid transform and remove it from the navmesh before we despawn it
 
@@ -439,11 +567,9 @@ pub fn mine_stone(
                     continue;
                 }
 
-                let stone_grid = stone_transform.translation.world_pos_to_tile();
-                navmesh.0[stone_grid.x as usize][stone_grid.y as usize].walkable = true;
-                navmesh.0[stone_grid.x as usize][stone_grid.y as usize]
-                    .occupied_by
-                    .remove(&stone_entity);
+                let stone_grid = stone_transform.tile_pos();
+                navmesh.set_walkable(stone_grid, true);
+                navmesh.vacate(stone_grid, stone_entity);
 
                 commands.entity(stone_entity).despawn_recursive();
                 commands
@@ -456,6 +582,80 @@ pub fn mine_stone(
     }
 }
 
+pub fn chop_tree(
+    mut commands: Commands,
+    q_pawns_moving_to_tree: Query<
+        (Entity, &Pawn),
+        (
+            With<PawnStatus<pawn_status::Moving>>,
+            With<WorkOrder<ChopTree>>,
+            Without<PawnStatus<pawn_status::Chopping>>,
+        ),
+    >,
+    mut q_pawns: Query<
+        (Entity, &Pawn, &mut CarriedResources, &WorkOrder<ChopTree>),
+        (
+            With<PawnStatus<pawn_status::Chopping>>,
+            Without<PawnStatus<pawn_status::Moving>>,
+        ),
+    >,
+    mut q_trees: Query<(Entity, &mut Tree, &Transform), With<TreeKind>>,
+    mut navmesh: ResMut<Navmesh>,
+) {
+    let mut felled_trees = HashSet::<Entity>::default();
+    for (pawn_entity, pawn) in &q_pawns_moving_to_tree {
+        if !pawn.moving {
+            commands
+                .entity(pawn_entity)
+                .add_status(pawn_status::Chopping);
+        }
+    }
+
+    for (pawn_entity, pawn, mut carried_resources, work_order) in &mut q_pawns {
+        if carried_resources.total() >= MAX_RESOURCES {
+            commands
+                .entity(pawn_entity)
+                .add_status(pawn_status::Idle)
+                .add_work_order(work_order::ReturnToFactory {});
+
+            continue;
+        }
+
+        if pawn.mine_timer.finished() {
+            let Ok((tree_entity, mut tree, tree_transform)) =
+                q_trees.get_mut(work_order.0.tree_entity)
+            else {
+                commands
+                    .entity(pawn_entity)
+                    .clear_work_order()
+                    .add_status(pawn_status::Idle);
+                continue;
+            };
+
+            if tree.remaining_resources > 0 {
+                tree.remaining_resources =
+                    tree.remaining_resources.saturating_sub(RESOURCE_GAIN_RATE);
+                carried_resources.add(ResourceKind::Wood, RESOURCE_GAIN_RATE);
+            } else {
+                if felled_trees.contains(&tree_entity) {
+                    continue;
+                }
+
+                let tree_grid = tree_transform.tile_pos();
+                navmesh.set_walkable(tree_grid, true);
+                navmesh.vacate(tree_grid, tree_entity);
+
+                commands.entity(tree_entity).despawn_recursive();
+                commands
+                    .entity(pawn_entity)
+                    .clear_work_order()
+                    .add_status(pawn_status::Idle);
+                felled_trees.insert(tree_entity);
+            }
+        }
+    }
+}
+
 pub fn return_to_factory(
     mut commands: Commands,
     q_pawns_need_pathfinding_to_factory: Query<
@@ -475,28 +675,31 @@ pub fn return_to_factory(
         ),
     >,
     q_factory: Query<&Transform, (With<Factory>, With<Placed>)>,
-    mut resources: ResMut<GameResources>,
+    mut ledger: ResMut<ResourceLedger>,
     mut pathfinding_event_writer: EventWriter<PathfindRequest>,
 ) {
     let Ok(factory_transform) = q_factory.get_single() else {
         return;
     };
 
-    let factory_grid = factory_transform.translation.world_pos_to_tile();
-
     // Loop through idle pawns that are looking for the factory
     for (pawn_entity, transform) in &q_pawns_need_pathfinding_to_factory {
-        let pawn_location = transform.translation.world_pos_to_tile();
+        let pawn_location = transform.tile_pos();
+
+        let request = PathfindRequest::new(
+            pawn_location,
+            factory_goal(factory_transform.tile_pos()),
+            pawn_entity,
+        )
+        .smoothed();
 
         commands
             .entity(pawn_entity)
-            .add_status(pawn_status::Pathfinding);
+            .add_status(pawn_status::Pathfinding {
+                request: request.id,
+            });
 
-        pathfinding_event_writer.send(PathfindRequest {
-            start: pawn_location,
-            end: factory_grid,
-            entity: pawn_entity,
-        });
+        pathfinding_event_writer.send(request);
     }
 
     // Loop through pawns that are moving to the factory looking for stopped pawns
@@ -508,28 +711,28 @@ pub fn return_to_factory(
                 .clear_work_order()
                 .add_status(pawn_status::Idle);
 
-            resources.stone += carried_resources.0;
-            carried_resources.0 = 0;
+            ledger.take_all(&mut carried_resources);
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn listen_for_spawn_pawn_event(
     mut commands: Commands,
     pawn_res: Res<MalePawns>,
     q_factory: Query<&GlobalTransform, (With<Factory>, With<Placed>)>,
     mut game_resources: ResMut<GameResources>,
+    mut ledger: ResMut<ResourceLedger>,
     mut spawn_pawn_event_reader: EventReader<SpawnPawnRequestEvent>,
     navmesh: Res<Navmesh>,
+    mut game_rng: ResMut<GameRng>,
 ) {
     let Ok(factory_transform) = q_factory.get_single() else {
         return;
     };
 
     for _ in spawn_pawn_event_reader.read() {
-        if game_resources.stone >= PAWN_COST {
-            game_resources.stone -= 100;
-        } else {
+        if !ledger.try_pay(&PAWN_COST) {
             continue;
         }
         spawn_pawn_in_random_location(
@@ -538,6 +741,7 @@ pub fn listen_for_spawn_pawn_event(
             &mut game_resources,
             factory_transform,
             &navmesh,
+            &mut game_rng.pawns,
         );
     }
 }
@@ -551,15 +755,40 @@ pub fn tick_timers(mut q_pawns: Query<&mut Pawn>, time: Res<Time>) {
     }
 }
 
+/// Colony pawns whose last path request failed
+type StuckPawns<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static mut Pawn, &'static Transform),
+    (
+        With<PawnStatus<pawn_status::PathfindingError>>,
+        Without<Enemy>,
+    ),
+>;
+
+/// Enemies whose last path request failed
+type StuckEnemies<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static mut Pawn),
+    (With<PawnStatus<pawn_status::PathfindingError>>, With<Enemy>),
+>;
+
 pub fn retry_pathfinding(
     mut commands: Commands,
-    mut q_pawns: Query<
-        (Entity, &mut Pawn, &Transform),
-        With<PawnStatus<pawn_status::PathfindingError>>,
-    >,
+    mut q_pawns: StuckPawns,
+    mut q_enemy_pawns: StuckEnemies,
     q_factory: Query<&GlobalTransform, (With<Factory>, With<Placed>)>,
     mut pathfinding_event_writer: EventWriter<PathfindRequest>,
 ) {
+    // idle enemies are sent after the factory again by enemy_search_for_factory
+    for (entity, mut pawn) in &mut q_enemy_pawns {
+        if pawn.retry_pathfinding_timer.finished() {
+            pawn.retry_pathfinding_timer.reset();
+            commands.entity(entity).add_status(pawn_status::Idle);
+        }
+    }
+
     let mut pathfinding_requests = Vec::new();
     let Ok(factory_transform) = q_factory.get_single() else {
         return;
@@ -569,20 +798,25 @@ pub fn retry_pathfinding(
             continue;
         }
 
-        commands
-            .entity(entity)
-            .clear_work_order()
-            .add_status(pawn_status::Idle);
-
         pawn.retry_pathfinding_timer.reset();
 
-        let pawn_pos = pawn_transform.translation.world_pos_to_tile();
-        let factory_pos = factory_transform.translation().world_pos_to_tile();
-        pathfinding_requests.push(PathfindRequest {
-            start: pawn_pos,
-            end: factory_pos,
+        let request = PathfindRequest::new(
+            pawn_transform.tile_pos(),
+            factory_goal(factory_transform.tile_pos()),
             entity,
-        });
+        )
+        .with_priority(PathPriority::Low)
+        .smoothed();
+
+        // head back to the factory like any other returning pawn, so the answer isn't thrown away
+        commands
+            .entity(entity)
+            .add_work_order(work_order::ReturnToFactory {})
+            .add_status(pawn_status::Pathfinding {
+                request: request.id,
+            });
+
+        pathfinding_requests.push(request);
 
         info!("Pawn {:?} is retrying pathfinding", entity);
     }
@@ -613,9 +847,9 @@ pub fn search_for_attack_target_pawn(
     #[derive(Debug)]
     struct PawnAttacking {
         pawn_entity: Entity,
-        pawn_location: Vec2,
+        pawn_location: TilePos,
         target_entity: Entity,
-        target_location: Vec2,
+        target_location: TilePos,
     }
     fn find_pawns_to_attack(
         search_query: &Query<(Entity, &Pawn, &Transform), impl ReadOnlyWorldQuery>,
@@ -626,17 +860,16 @@ pub fn search_for_attack_target_pawn(
             if !pawn.search_timer.finished() {
                 continue;
             }
-            let pawn_position = transform.world_pos_to_tile();
+            let pawn_position = transform.tile_pos();
             let mut results = to_attack_query
                 .iter()
                 .filter(|&(_, _, enemy_pos)| {
-                    let enemy_position = enemy_pos.world_pos_to_tile();
-                    (enemy_position - pawn_position).length() <= ENEMY_TILE_RANGE as f32
+                    enemy_pos.tile_pos().distance(pawn_position) <= ENEMY_TILE_RANGE as f32
                 })
                 .collect::<Vec<_>>();
             results.sort_by(|&(_, _, a), &(_, _, b)| {
-                let a_distance = (a.world_pos_to_tile() - pawn_position).length();
-                let b_distance = (b.world_pos_to_tile() - pawn_position).length();
+                let a_distance = a.tile_pos().distance(pawn_position);
+                let b_distance = b.tile_pos().distance(pawn_position);
                 a_distance.partial_cmp(&b_distance).unwrap()
             });
             let Some((enemy_entity, _, enemy_transform)) = results.into_iter().next() else {
@@ -647,7 +880,7 @@ pub fn search_for_attack_target_pawn(
                 pawn_entity,
                 target_entity: enemy_entity,
                 pawn_location: pawn_position,
-                target_location: enemy_transform.world_pos_to_tile(),
+                target_location: enemy_transform.tile_pos(),
             };
 
             if attack_map.contains_key(&enemy_entity) {
@@ -679,11 +912,12 @@ pub fn search_for_attack_target_pawn(
                      target_entity,
                  }| {
                     (
-                        PathfindRequest {
-                            start: pawn_location,
-                            end: target_location,
-                            entity: pawn_entity,
-                        },
+                        PathfindRequest::new(
+                            pawn_location,
+                            attack_goal(target_location),
+                            pawn_entity,
+                        )
+                        .with_priority(PathPriority::High),
                         target_entity,
                     )
                 },
@@ -691,10 +925,10 @@ pub fn search_for_attack_target_pawn(
         })
         .collect::<Vec<_>>();
 
-    for &(PathfindRequest { entity, .. }, target_entity) in &nav_requests {
+    for &(PathfindRequest { id, entity, .. }, target_entity) in &nav_requests {
         commands
             .entity(entity)
-            .add_status(pawn_status::Pathfinding)
+            .add_status(pawn_status::Pathfinding { request: id })
             .add_work_order(work_order::AttackPawn {
                 pawn_entity: target_entity,
             });
@@ -703,6 +937,7 @@ pub fn search_for_attack_target_pawn(
     pathfinding_event_writer.send_batch(nav_requests.into_iter().map(|(r, _)| r));
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_enemy_pawns(
     mut commands: Commands,
     mut enemy_wave: ResMut<EnemyWave>,
@@ -711,8 +946,10 @@ pub fn spawn_enemy_pawns(
     navmesh: Res<Navmesh>,
     input: Query<&ActionState<crate::Input>>,
     mouse_position: Res<CursorPosition>,
+    mut game_rng: ResMut<GameRng>,
+    q_factory: Query<&GlobalTransform, (With<Factory>, With<Placed>)>,
 ) {
-    let mut spawn_enemy = move |spawn_location: Vec2| {
+    let mut spawn_enemy = move |spawn_location: WorldPos, texture_atlas: Handle<TextureAtlas>| {
         let pawn_entity = commands
             .spawn(PawnBundle {
                 pawn: Pawn {
@@ -725,16 +962,13 @@ pub fn spawn_enemy_pawns(
                     mine_timer: Timer::from_seconds(0.5, TimerMode::Once),
                     retry_pathfinding_timer: Timer::from_seconds(1., TimerMode::Once),
                     moving: false,
+                    path_request: None,
                 },
                 character_facing: CharacterFacing::Left,
                 name: Name::new("Enemy"),
                 sprite_bundle: SpriteSheetBundle {
-                    texture_atlas: pawn_res.get_random(),
-                    transform: Transform::from_translation(Vec3::new(
-                        spawn_location.x,
-                        spawn_location.y,
-                        1.,
-                    )),
+                    texture_atlas,
+                    transform: Transform::from_translation(spawn_location.extend(1.)),
                     sprite: TextureAtlasSprite {
                         anchor: bevy::sprite::Anchor::BottomLeft,
                         index: CharacterFacing::Left as usize,
@@ -744,7 +978,7 @@ pub fn spawn_enemy_pawns(
                     ..Default::default()
                 },
                 pawn_status: PawnStatus(Box::new(pawn_status::Idle)),
-                resources: CarriedResources(0),
+                resources: CarriedResources::default(),
             })
             .insert(Enemy)
             .id();
@@ -771,7 +1005,10 @@ pub fn spawn_enemy_pawns(
 
     if input.just_pressed(crate::Input::DebugSpawnPawn) && mouse_position.0.is_some() {
         #[cfg(debug_assertions)]
-        spawn_enemy(mouse_position.0.unwrap().tile_pos_to_world());
+        spawn_enemy(
+            mouse_position.0.unwrap().corner(),
+            pawn_res.get_random(&mut game_rng.waves),
+        );
     }
 
     enemy_wave.enemy_spawn_timer.tick(time.delta());
@@ -781,43 +1018,40 @@ pub fn spawn_enemy_pawns(
     }
     enemy_wave.wave += 1;
 
-    for _ in 0..enemy_wave.wave * enemy_wave.enemy_count_multiplier {
-        // get a random boolean true or false
-        let mut rng = rand::thread_rng();
-        let spawn_x = rng.gen_bool(0.5);
-
-        let spawn_location: Vec2;
-
-        loop {
-            let temp_location: (usize, usize) = if spawn_x {
-                // randomly choose between 0 or SIZE (left or right)
-                let x: usize = if rng.gen_bool(0.5) { SIZE } else { 0 };
-                let y = rng.gen_range(0..SIZE - 1);
+    let factory_pos = q_factory
+        .get_single()
+        .ok()
+        .map(|factory| factory.tile_pos());
+
+    // enemies can only spawn on walkable tiles along the edge of the map, never in water or inside stone, and
+    // only where they can actually walk to the factory from
+    let mut spawn_locations = navmesh
+        .tiles()
+        .edges()
+        .filter(|pos| {
+            matches!(
+                navmesh.get(*pos),
+                Some(NavTileOccupant { walkable: true, .. })
+            )
+        })
+        .collect::<Vec<_>>();
+    if let Some(factory) = factory_pos {
+        spawn_locations.retain(|pos| navmesh.is_reachable(*pos, factory));
+    }
 
-                (x, y)
-            } else {
-                let x = rng.gen_range(0..SIZE - 1);
-                let y: usize = if rng.gen_bool(0.5) { SIZE } else { 0 };
-                (x, y)
-            };
+    if spawn_locations.is_empty() {
+        warn!("No walkable tiles on the edge of the map to spawn enemies on");
+        return;
+    }
 
-            // check navtile to ensure it's walkable
-            if let Some(NavTileOccupant { walkable, .. }) = navmesh
-                .0
-                .get(temp_location.0)
-                .and_then(|o| o.get(temp_location.1))
-            {
-                if *walkable {
-                    spawn_location = Vec2::new(temp_location.0 as f32, temp_location.1 as f32);
-                    break;
-                }
-            }
-        }
+    for _ in 0..enemy_wave.wave * enemy_wave.enemy_count_multiplier {
+        let Some(spawn_location) = spawn_locations.choose(&mut game_rng.waves) else {
+            continue;
+        };
 
         // convert spawn_location to world coordinates
-        let spawn_location = spawn_location.tile_pos_to_world();
-        spawn_enemy(spawn_location);
-        // spawn enemy pawn
+        let texture_atlas = pawn_res.get_random(&mut game_rng.waves);
+        spawn_enemy(spawn_location.corner(), texture_atlas);
     }
 }
 
@@ -825,6 +1059,7 @@ pub fn enemy_search_for_factory(
     mut commands: Commands,
     q_enemy_pawns: Query<(Entity, &Transform), (With<Enemy>, With<PawnStatus<pawn_status::Idle>>)>,
     q_factory: Query<&GlobalTransform, (With<Factory>, With<Placed>)>,
+    flow_field: Res<FlowField>,
     mut nav_request: EventWriter<PathfindRequest>,
 ) {
     let Ok(factory) = q_factory.get_single() else {
@@ -832,21 +1067,67 @@ pub fn enemy_search_for_factory(
     };
 
     for (entity, transform) in &q_enemy_pawns {
-        let grid_location = transform.translation.world_pos_to_tile();
+        let grid_location = transform.tile_pos();
 
-        nav_request.send(PathfindRequest {
-            start: grid_location,
-            end: factory.translation().world_pos_to_tile(),
-            entity,
-        });
+        // the flow field already knows the way, only fall back to A* if it hasn't reached this tile yet
+        if flow_field.cost(grid_location).is_some() {
+            commands
+                .entity(entity)
+                .add_status(pawn_status::Moving)
+                .add_work_order(work_order::AttackFactory {});
+            continue;
+        }
+
+        let request = PathfindRequest::new(grid_location, factory_goal(factory.tile_pos()), entity)
+            .smoothed();
+        nav_request.send(request);
 
         commands
             .entity(entity)
-            .add_status(pawn_status::Pathfinding)
+            .add_status(pawn_status::Pathfinding {
+                request: request.id,
+            })
             .add_work_order(work_order::AttackFactory {});
     }
 }
 
+/// Enemies on their way to the factory, and whether they're currently moving
+type FlowFieldFollowers<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Pawn,
+        &'static Transform,
+        Has<PawnStatus<pawn_status::Moving>>,
+    ),
+    With<WorkOrder<work_order::AttackFactory>>,
+>;
+
+/// Walks enemies heading for the factory one tile at a time along the shared flow field
+pub fn follow_flow_field(
+    mut q_enemy_pawns: FlowFieldFollowers,
+    flow_field: Res<FlowField>,
+    navmesh: Res<Navmesh>,
+) {
+    for (mut pawn, transform, moving) in &mut q_enemy_pawns {
+        if !moving || pawn.move_to.is_some() || !pawn.move_path.is_empty() {
+            continue;
+        }
+
+        let Some(next) = flow_field.next_step(transform.tile_pos()) else {
+            continue;
+        };
+
+        // the field may still lead through a tile blocked since it was computed, wait for the new one instead
+        if !navmesh.get(next).is_some_and(|tile| tile.walkable) {
+            continue;
+        }
+
+        pawn.move_path.push_back(next);
+        pawn.path_request = None;
+    }
+}
+
 pub fn update_pathfinding_to_pawn(
     mut commands: Commands,
     q_all_attacking_pawns: Query<
@@ -874,9 +1155,9 @@ pub fn update_pathfinding_to_pawn(
             continue;
         };
 
-        let distance_to_target = (target_transform.translation.world_pos_to_tile()
-            - pawn_transform.translation.world_pos_to_tile())
-        .length();
+        let distance_to_target = target_transform
+            .tile_pos()
+            .distance(pawn_transform.tile_pos());
 
         if distance_to_target <= 2. {
             commands.entity(entity).add_status(pawn_status::Attacking);
@@ -887,12 +1168,19 @@ pub fn update_pathfinding_to_pawn(
             continue;
         }
 
-        pathfinding_event_writer.send(PathfindRequest {
-            start: pawn_transform.translation.world_pos_to_tile(),
-            end: target_transform.translation.world_pos_to_tile(),
+        // the target keeps moving and the path is redone every search, so it's not worth smoothing
+        let request = PathfindRequest::new(
+            pawn_transform.tile_pos(),
+            attack_goal(target_transform.tile_pos()),
             entity,
-        });
-        commands.entity(entity).add_status(pawn_status::Pathfinding);
+        )
+        .with_priority(PathPriority::High);
+        pathfinding_event_writer.send(request);
+        commands
+            .entity(entity)
+            .add_status(pawn_status::Pathfinding {
+                request: request.id,
+            });
     }
 }
 
@@ -1013,16 +1301,23 @@ pub fn attack_pawn(
             continue;
         };
 
-        let distance_to_target = attacking_entity_transform.translation.world_pos_to_tile()
-            - entity_transform.translation.world_pos_to_tile();
+        let distance_to_target = attacking_entity_transform
+            .tile_pos()
+            .distance(entity_transform.tile_pos());
 
-        if distance_to_target.length() > 2. {
-            commands.entity(entity).add_status(pawn_status::Pathfinding);
-            pathfinding_event_writer.send(PathfindRequest {
-                start: entity_transform.translation.world_pos_to_tile(),
-                end: attacking_entity_transform.translation.world_pos_to_tile(),
+        if distance_to_target > 2. {
+            let request = PathfindRequest::new(
+                entity_transform.tile_pos(),
+                attack_goal(attacking_entity_transform.tile_pos()),
                 entity,
-            });
+            )
+            .with_priority(PathPriority::High);
+            commands
+                .entity(entity)
+                .add_status(pawn_status::Pathfinding {
+                    request: request.id,
+                });
+            pathfinding_event_writer.send(request);
             continue;
         }
 
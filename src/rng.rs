@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use rand::{prelude::*, rngs::StdRng};
+
+/// The seeded source of randomness for a game. Every system which shapes the world draws from one of the
+/// sub-streams below, so a given seed always produces the same map, stones and waves, and adding a new
+/// random call to one system doesn't shift the results of every other system.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    pub terrain: StdRng,
+    pub stones: StdRng,
+    pub trees: StdRng,
+    pub pawns: StdRng,
+    pub waves: StdRng,
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            terrain: stream(seed, "terrain"),
+            stones: stream(seed, "stones"),
+            trees: stream(seed, "trees"),
+            pawns: stream(seed, "pawns"),
+            waves: stream(seed, "waves"),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::from_seed(random())
+    }
+}
+
+fn stream(seed: u64, name: &str) -> StdRng {
+    StdRng::seed_from_u64(seed ^ hash_str(name))
+}
+
+/// Turns user input into a seed. Numbers are used as-is so seeds can be copied out of logs, anything else is
+/// hashed so a word or phrase works as a seed too. Returns `None` for empty input.
+pub fn parse_seed(input: &str) -> Option<u64> {
+    let input = input.trim();
+
+    if input.is_empty() {
+        return None;
+    }
+
+    Some(input.parse::<u64>().unwrap_or_else(|_| hash_str(input)))
+}
+
+/// FNV-1a, used instead of the std hasher because its output must never change between builds
+fn hash_str(input: &str) -> u64 {
+    input.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
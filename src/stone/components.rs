@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use std::ops::RangeInclusive;
 
 #[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum StoneKind {
@@ -9,7 +10,75 @@ pub enum StoneKind {
     Tan,
 }
 
+/// How a kind of stone is spread across the map as veins
+#[derive(Debug, Clone)]
+pub struct VeinSettings {
+    /// How many resources a single tile of the vein holds
+    pub richness: RangeInclusive<usize>,
+    /// The chance of each tile which is rich enough starting a vein
+    pub rarity: f64,
+    /// Veins only start where the resource noise is at least this value, so the rarer kinds gather in the
+    /// richest parts of the map
+    pub min_resource_noise: f32,
+    /// How many tiles a vein grows to
+    pub vein_size: RangeInclusive<usize>,
+}
+
+impl StoneKind {
+    /// Every kind of stone, from the rarest to the most common
+    pub const BY_RARITY: [StoneKind; 5] = [
+        StoneKind::Capped,
+        StoneKind::Red,
+        StoneKind::Salt,
+        StoneKind::Tan,
+        StoneKind::Stone,
+    ];
+
+    pub fn vein_settings(self) -> VeinSettings {
+        match self {
+            StoneKind::Capped => VeinSettings {
+                richness: 1800..=3000,
+                rarity: 0.001,
+                min_resource_noise: 0.6,
+                vein_size: 2..=6,
+            },
+            StoneKind::Red => VeinSettings {
+                richness: 1200..=2000,
+                rarity: 0.0015,
+                min_resource_noise: 0.4,
+                vein_size: 3..=8,
+            },
+            StoneKind::Salt => VeinSettings {
+                richness: 800..=1500,
+                rarity: 0.002,
+                min_resource_noise: 0.2,
+                vein_size: 4..=10,
+            },
+            StoneKind::Tan => VeinSettings {
+                richness: 700..=1200,
+                rarity: 0.003,
+                min_resource_noise: 0.,
+                vein_size: 5..=12,
+            },
+            StoneKind::Stone => VeinSettings {
+                richness: 600..=1000,
+                rarity: 0.004,
+                min_resource_noise: -0.2,
+                vein_size: 6..=14,
+            },
+        }
+    }
+}
+
+/// A stone as generated, before it is spawned into the world
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StoneDeposit {
+    pub kind: StoneKind,
+    pub amount: usize,
+}
+
 #[derive(Component, Debug)]
 pub struct Stone {
     pub remaining_resources: usize,
+    pub max_resources: usize,
 }
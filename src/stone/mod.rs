@@ -1,10 +1,11 @@
 mod components;
 mod systems;
 
-use crate::{build_map, GameState};
+use crate::{build_map, utils::TilePos, GameState};
 use bevy::prelude::*;
 
 pub use components::*;
+pub use systems::spawn_stone_tiles;
 
 pub struct StonePlugin;
 
@@ -25,6 +26,6 @@ impl Plugin for StonePlugin {
 #[derive(Event, Debug)]
 pub struct DropStone {
     pub stone_kind: StoneKind,
-    pub location: Vec2,
+    pub location: TilePos,
     pub amount: usize,
 }
@@ -0,0 +1,217 @@
+use crate::{
+    assets::DirtTile,
+    grid::{Grid, NEIGHBORS_8},
+    utils::TilePos,
+};
+
+/// Which of the 8 neighbors of a tile belong to the other terrain of an autotiled pair. Each bit is one
+/// neighbor, in the same order as [`NEIGHBORS_8`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct NeighborMask(pub u8);
+
+impl NeighborMask {
+    pub const UP: u8 = 1 << 0;
+    pub const DOWN: u8 = 1 << 1;
+    pub const LEFT: u8 = 1 << 2;
+    pub const RIGHT: u8 = 1 << 3;
+    pub const UP_LEFT: u8 = 1 << 4;
+    pub const UP_RIGHT: u8 = 1 << 5;
+    pub const DOWN_LEFT: u8 = 1 << 6;
+    pub const DOWN_RIGHT: u8 = 1 << 7;
+
+    /// Build the mask for `pos`. Neighbors outside the grid never count as the other terrain.
+    pub fn from_grid<T>(grid: &Grid<T>, pos: TilePos, is_other: impl Fn(&T) -> bool) -> Self {
+        let bits = NEIGHBORS_8
+            .iter()
+            .enumerate()
+            .filter(|(_, offset)| grid.get(pos + **offset).is_some_and(&is_other))
+            .fold(0, |bits, (index, _)| bits | 1 << index);
+
+        Self(bits)
+    }
+
+    /// True if every bit in `bits` is set
+    pub fn has(self, bits: u8) -> bool {
+        self.0 & bits == bits
+    }
+
+    /// The up, down, left and right bits on their own
+    pub fn sides(self) -> u8 {
+        self.0 & (Self::UP | Self::DOWN | Self::LEFT | Self::RIGHT)
+    }
+
+    /// Drop every diagonal which touches a side that's already set. An edge sprite already draws the corners
+    /// at both of its ends, so those diagonals never change which sprite fits. This brings the 256 possible
+    /// masks down to the 47 that actually look different.
+    pub fn reduced(self) -> Self {
+        let mut bits = self.0;
+
+        for (corner, side_a, side_b) in [
+            (Self::UP_LEFT, Self::UP, Self::LEFT),
+            (Self::UP_RIGHT, Self::UP, Self::RIGHT),
+            (Self::DOWN_LEFT, Self::DOWN, Self::LEFT),
+            (Self::DOWN_RIGHT, Self::DOWN, Self::RIGHT),
+        ] {
+            if self.0 & (side_a | side_b) != 0 {
+                bits &= !corner;
+            }
+        }
+
+        Self(bits)
+    }
+}
+
+/// A set of sprites for the border between two terrains, picked from which neighbors are the other terrain
+pub trait Autotile: Sized {
+    fn from_mask(mask: NeighborMask) -> Self;
+}
+
+/// Pick the sprite for the tile at `pos`, where `is_other` says which tiles belong to the other terrain
+pub fn autotile<T, A: Autotile>(grid: &Grid<T>, pos: TilePos, is_other: impl Fn(&T) -> bool) -> A {
+    A::from_mask(NeighborMask::from_grid(grid, pos, is_other).reduced())
+}
+
+impl Autotile for DirtTile {
+    fn from_mask(mask: NeighborMask) -> Self {
+        const UP: u8 = NeighborMask::UP;
+        const DOWN: u8 = NeighborMask::DOWN;
+        const LEFT: u8 = NeighborMask::LEFT;
+        const RIGHT: u8 = NeighborMask::RIGHT;
+
+        match mask.sides() {
+            0 => {
+                // only the outer corners are left. The spritesheet has one corner per tile, so when grass
+                // touches more than one corner the upper ones win since they sit in front on screen.
+                if mask.has(NeighborMask::UP_LEFT) {
+                    DirtTile::OutsideTopLeft
+                } else if mask.has(NeighborMask::UP_RIGHT) {
+                    DirtTile::OutsideTopRight
+                } else if mask.has(NeighborMask::DOWN_LEFT) {
+                    DirtTile::OutsideBottomLeft
+                } else if mask.has(NeighborMask::DOWN_RIGHT) {
+                    DirtTile::OutsideBottomRight
+                } else {
+                    DirtTile::MiddleMiddle
+                }
+            }
+            UP => DirtTile::TopMiddle,
+            DOWN => DirtTile::BottomMiddle,
+            LEFT => DirtTile::MiddleLeft,
+            RIGHT => DirtTile::MiddleRight,
+            sides if sides == UP | LEFT => DirtTile::TopLeft,
+            sides if sides == UP | RIGHT => DirtTile::TopRight,
+            sides if sides == DOWN | LEFT => DirtTile::BottomLeft,
+            sides if sides == DOWN | RIGHT => DirtTile::BottomRight,
+            sides if sides == UP | DOWN => DirtTile::TopAndBottom,
+            sides if sides == LEFT | RIGHT => DirtTile::LeftAndRight,
+            sides if sides == UP | LEFT | RIGHT => DirtTile::OpenBottom,
+            sides if sides == DOWN | LEFT | RIGHT => DirtTile::OpenTop,
+            sides if sides == UP | DOWN | LEFT => DirtTile::OpenRight,
+            sides if sides == UP | DOWN | RIGHT => DirtTile::OpenLeft,
+            _ => DirtTile::Surrounded,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UP: u8 = NeighborMask::UP;
+    const DOWN: u8 = NeighborMask::DOWN;
+    const LEFT: u8 = NeighborMask::LEFT;
+    const RIGHT: u8 = NeighborMask::RIGHT;
+    const UP_LEFT: u8 = NeighborMask::UP_LEFT;
+    const UP_RIGHT: u8 = NeighborMask::UP_RIGHT;
+    const DOWN_LEFT: u8 = NeighborMask::DOWN_LEFT;
+    const DOWN_RIGHT: u8 = NeighborMask::DOWN_RIGHT;
+
+    fn dirt(bits: u8) -> DirtTile {
+        DirtTile::from_mask(NeighborMask(bits).reduced())
+    }
+
+    #[test]
+    fn reduced_drops_corners_next_to_sides() {
+        assert_eq!(
+            NeighborMask(UP | UP_LEFT | UP_RIGHT | DOWN_LEFT).reduced(),
+            NeighborMask(UP | DOWN_LEFT)
+        );
+        assert_eq!(
+            NeighborMask(LEFT | UP_LEFT | DOWN_LEFT | DOWN_RIGHT).reduced(),
+            NeighborMask(LEFT | DOWN_RIGHT)
+        );
+        assert_eq!(
+            NeighborMask(0xff).reduced(),
+            NeighborMask(UP | DOWN | LEFT | RIGHT)
+        );
+    }
+
+    #[test]
+    fn reduced_keeps_lone_corners() {
+        let corners = UP_LEFT | UP_RIGHT | DOWN_LEFT | DOWN_RIGHT;
+        assert_eq!(NeighborMask(corners).reduced(), NeighborMask(corners));
+    }
+
+    #[test]
+    fn reduced_leaves_47_distinct_masks() {
+        let mut masks = (0..=u8::MAX)
+            .map(|bits| NeighborMask(bits).reduced())
+            .collect::<Vec<_>>();
+        masks.sort_by_key(|mask| mask.0);
+        masks.dedup();
+
+        assert_eq!(masks.len(), 47);
+    }
+
+    #[test]
+    fn sides_pick_edges() {
+        assert_eq!(dirt(0), DirtTile::MiddleMiddle);
+        assert_eq!(dirt(UP), DirtTile::TopMiddle);
+        assert_eq!(dirt(DOWN), DirtTile::BottomMiddle);
+        assert_eq!(dirt(LEFT), DirtTile::MiddleLeft);
+        assert_eq!(dirt(RIGHT), DirtTile::MiddleRight);
+        assert_eq!(dirt(UP | LEFT | UP_LEFT), DirtTile::TopLeft);
+        assert_eq!(dirt(UP | RIGHT), DirtTile::TopRight);
+        assert_eq!(dirt(DOWN | LEFT), DirtTile::BottomLeft);
+        assert_eq!(dirt(DOWN | RIGHT | DOWN_RIGHT), DirtTile::BottomRight);
+        assert_eq!(dirt(UP | DOWN), DirtTile::TopAndBottom);
+        assert_eq!(dirt(LEFT | RIGHT), DirtTile::LeftAndRight);
+    }
+
+    #[test]
+    fn dead_ends_and_lone_tiles() {
+        assert_eq!(dirt(UP | LEFT | RIGHT), DirtTile::OpenBottom);
+        assert_eq!(dirt(DOWN | LEFT | RIGHT), DirtTile::OpenTop);
+        assert_eq!(dirt(UP | DOWN | LEFT), DirtTile::OpenRight);
+        assert_eq!(dirt(UP | DOWN | RIGHT), DirtTile::OpenLeft);
+        assert_eq!(dirt(0xff), DirtTile::Surrounded);
+    }
+
+    #[test]
+    fn upper_outer_corners_win() {
+        assert_eq!(dirt(DOWN_RIGHT), DirtTile::OutsideBottomRight);
+        assert_eq!(dirt(DOWN_LEFT | DOWN_RIGHT), DirtTile::OutsideBottomLeft);
+        assert_eq!(dirt(UP_RIGHT | DOWN_LEFT), DirtTile::OutsideTopRight);
+        assert_eq!(dirt(UP_LEFT | UP_RIGHT), DirtTile::OutsideTopLeft);
+    }
+
+    #[test]
+    fn dead_ends_render_as_matching_edges() {
+        assert_eq!(
+            DirtTile::OpenBottom.atlas_index(),
+            DirtTile::TopMiddle.atlas_index()
+        );
+        assert_eq!(
+            DirtTile::OpenTop.atlas_index(),
+            DirtTile::BottomMiddle.atlas_index()
+        );
+        assert_eq!(
+            DirtTile::OpenRight.atlas_index(),
+            DirtTile::MiddleLeft.atlas_index()
+        );
+        assert_eq!(
+            DirtTile::OpenLeft.atlas_index(),
+            DirtTile::MiddleRight.atlas_index()
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use super::autotile::autotile;
+use crate::{assets::DirtTile, grid::Grid, utils::TilePos};
+use bevy::{prelude::*, utils::HashSet};
+
+/// The width and height of a chunk in tiles
+pub const CHUNK_SIZE: i32 = 32;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum TileType {
+    #[default]
+    Water,
+    Dirt,
+    Grass,
+}
+
+/// A block of [`CHUNK_SIZE`] x [`CHUNK_SIZE`] ground tiles drawn as one child mesh per [`TileType`] instead
+/// of one sprite per tile
+#[derive(Component, Debug)]
+pub struct TilemapChunk;
+
+/// The ground of the whole map. Tiles are looked up here instead of through entities, and changing a tile
+/// marks the chunks that draw it as dirty so they get rebuilt at the end of the frame.
+#[derive(Resource, Default)]
+pub struct Tilemap {
+    tiles: Grid<TileType>,
+    /// The autotiled sprite of every dirt tile, kept up to date as tiles change
+    dirt_tiles: Grid<DirtTile>,
+    chunks: Grid<Option<Entity>>,
+    dirty: HashSet<TilePos>,
+}
+
+impl Tilemap {
+    pub fn new(tiles: Grid<TileType>) -> Self {
+        let chunks = Grid::new(
+            chunk_count(tiles.width() as i32),
+            chunk_count(tiles.height() as i32),
+        );
+        let dirty = chunks.positions().collect();
+        let dirt_tiles = Grid::from_fn(tiles.width(), tiles.height(), |pos| {
+            dirt_tile_at(&tiles, pos)
+        });
+
+        Self {
+            tiles,
+            dirt_tiles,
+            chunks,
+            dirty,
+        }
+    }
+
+    pub fn tiles(&self) -> &Grid<TileType> {
+        &self.tiles
+    }
+
+    pub fn get(&self, pos: TilePos) -> Option<TileType> {
+        self.tiles.get(pos).copied()
+    }
+
+    /// Change the tile at `pos`. Returns false if `pos` is outside the map.
+    pub fn set(&mut self, pos: TilePos, tile_type: TileType) -> bool {
+        let Some(tile) = self.tiles.get_mut(pos) else {
+            return false;
+        };
+
+        if *tile != tile_type {
+            *tile = tile_type;
+
+            // dirt picks its sprite from the surrounding tiles, so the neighbors may need redrawing too
+            for neighbor in self.tiles.neighbors_8(pos).chain([pos]) {
+                if let Some(dirt_tile) = self.dirt_tiles.get_mut(neighbor) {
+                    *dirt_tile = dirt_tile_at(&self.tiles, neighbor);
+                }
+                self.dirty.insert(Self::chunk_of(neighbor));
+            }
+        }
+
+        true
+    }
+
+    /// The sprite to draw the tile at `pos` with if it's dirt
+    pub fn dirt_tile(&self, pos: TilePos) -> Option<DirtTile> {
+        self.dirt_tiles.get(pos).copied()
+    }
+
+    /// The position of the chunk containing `pos`, in chunk coordinates
+    pub fn chunk_of(pos: TilePos) -> TilePos {
+        TilePos(pos.0.div_euclid(IVec2::splat(CHUNK_SIZE)))
+    }
+
+    /// The entity drawing the tile at `pos`, if its chunk has been spawned
+    pub fn chunk_entity(&self, pos: TilePos) -> Option<Entity> {
+        self.chunks.get(Self::chunk_of(pos)).copied().flatten()
+    }
+
+    pub(super) fn chunks_mut(&mut self) -> &mut Grid<Option<Entity>> {
+        &mut self.chunks
+    }
+
+    pub(super) fn take_dirty(&mut self) -> HashSet<TilePos> {
+        std::mem::take(&mut self.dirty)
+    }
+
+    pub(super) fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+}
+
+fn dirt_tile_at(tiles: &Grid<TileType>, pos: TilePos) -> DirtTile {
+    autotile(tiles, pos, |tile| *tile == TileType::Grass)
+}
+
+fn chunk_count(tiles: i32) -> usize {
+    (tiles + CHUNK_SIZE - 1).div_euclid(CHUNK_SIZE) as usize
+}
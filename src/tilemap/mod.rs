@@ -0,0 +1,24 @@
+pub mod autotile;
+mod components;
+mod systems;
+
+use crate::{build_map, GameState};
+use bevy::prelude::*;
+
+pub use components::*;
+
+pub struct TilemapPlugin;
+
+impl Plugin for TilemapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Tilemap>()
+            .add_systems(
+                OnEnter(GameState::WorldSpawn),
+                systems::update_chunks.after(build_map),
+            )
+            .add_systems(
+                PostUpdate,
+                systems::update_chunks.run_if(systems::chunks_dirty),
+            );
+    }
+}
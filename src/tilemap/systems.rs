@@ -0,0 +1,166 @@
+use super::{TileType, Tilemap, TilemapChunk, CHUNK_SIZE};
+use crate::{assets::GroundBase, navmesh::components::Navmesh, utils::TilePos, TILE_SIZE};
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    sprite::MaterialMesh2dBundle,
+    utils::HashMap,
+};
+
+/// The vertices of every tile of one [`TileType`] inside a chunk
+#[derive(Default)]
+struct LayerMesh {
+    positions: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+}
+
+impl LayerMesh {
+    /// Add a tile sized quad with its bottom left corner at `corner`, textured with the `uv_min` to `uv_max`
+    /// section of the layer texture
+    fn push_quad(&mut self, corner: Vec2, uv_min: Vec2, uv_max: Vec2) {
+        let start = self.positions.len() as u32;
+
+        self.positions.extend([
+            [corner.x, corner.y, 0.],
+            [corner.x + TILE_SIZE, corner.y, 0.],
+            [corner.x + TILE_SIZE, corner.y + TILE_SIZE, 0.],
+            [corner.x, corner.y + TILE_SIZE, 0.],
+        ]);
+        // texture coordinates start in the top left, world coordinates in the bottom left
+        self.uvs.extend([
+            [uv_min.x, uv_max.y],
+            [uv_max.x, uv_max.y],
+            [uv_max.x, uv_min.y],
+            [uv_min.x, uv_min.y],
+        ]);
+        self.indices
+            .extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+    }
+
+    fn into_mesh(self) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        let normals = vec![[0., 0., 1.]; self.positions.len()];
+
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+        mesh.set_indices(Some(Indices::U32(self.indices)));
+        mesh
+    }
+}
+
+fn build_chunk_meshes(
+    tilemap: &Tilemap,
+    chunk: TilePos,
+    dirt_atlas: &TextureAtlas,
+) -> HashMap<TileType, Mesh> {
+    let tiles = tilemap.tiles();
+    let origin = TilePos(chunk.0 * CHUNK_SIZE);
+    let mut layers = HashMap::<TileType, LayerMesh>::default();
+
+    for pos in tiles.rect(origin, origin + IVec2::splat(CHUNK_SIZE - 1)) {
+        let Some(tile_type) = tiles.get(pos) else {
+            continue;
+        };
+
+        let (uv_min, uv_max) = match tile_type {
+            TileType::Dirt => {
+                let dirt_tile = tilemap.dirt_tile(pos).unwrap_or_default();
+                let rect = dirt_atlas.textures[dirt_tile.atlas_index()];
+                (rect.min / dirt_atlas.size, rect.max / dirt_atlas.size)
+            }
+            TileType::Water | TileType::Grass => (Vec2::ZERO, Vec2::ONE),
+        };
+
+        // vertices are relative to the chunk entity, which sits on the chunk's first tile
+        let corner = (pos.0 - origin.0).as_vec2() * TILE_SIZE;
+        layers
+            .entry(*tile_type)
+            .or_default()
+            .push_quad(corner, uv_min, uv_max);
+    }
+
+    layers
+        .into_iter()
+        .map(|(tile_type, layer)| (tile_type, layer.into_mesh()))
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_chunks(
+    mut commands: Commands,
+    mut tilemap: ResMut<Tilemap>,
+    mut navmesh: ResMut<Navmesh>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut layer_materials: Local<HashMap<TileType, Handle<ColorMaterial>>>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    ground_texture: Res<GroundBase>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(dirt_atlas) = texture_atlases.get(&ground_texture.dirt) else {
+        return;
+    };
+
+    if layer_materials.is_empty() {
+        for (tile_type, texture) in [
+            (TileType::Water, ground_texture.water.clone()),
+            (TileType::Dirt, dirt_atlas.texture.clone()),
+            (TileType::Grass, asset_server.load("grass.png")),
+        ] {
+            layer_materials.insert(tile_type, materials.add(texture.into()));
+        }
+    }
+
+    for chunk in tilemap.take_dirty() {
+        let Some(existing_entity) = tilemap.chunks_mut().get(chunk).copied() else {
+            continue;
+        };
+
+        let origin = TilePos(chunk.0 * CHUNK_SIZE);
+
+        let chunk_entity = if let Some(chunk_entity) = existing_entity {
+            commands.entity(chunk_entity).despawn_descendants();
+            chunk_entity
+        } else {
+            let chunk_entity = commands
+                .spawn((
+                    Name::new(format!("Tilemap Chunk {}", chunk.0)),
+                    TilemapChunk,
+                    SpatialBundle::from_transform(Transform::from_translation(
+                        origin.corner().extend(0.),
+                    )),
+                ))
+                .id();
+
+            let tiles = navmesh
+                .tiles()
+                .rect(origin, origin + IVec2::splat(CHUNK_SIZE - 1))
+                .collect::<Vec<_>>();
+            for pos in tiles {
+                navmesh.occupy(pos, chunk_entity);
+            }
+
+            *tilemap.chunks_mut().get_mut(chunk).unwrap() = Some(chunk_entity);
+            chunk_entity
+        };
+
+        for (tile_type, mesh) in build_chunk_meshes(&tilemap, chunk, dirt_atlas) {
+            commands
+                .spawn((
+                    MaterialMesh2dBundle {
+                        mesh: meshes.add(mesh).into(),
+                        material: layer_materials[&tile_type].clone(),
+                        ..default()
+                    },
+                    Name::new(format!("{:?} Layer", tile_type)),
+                ))
+                .set_parent(chunk_entity);
+        }
+    }
+}
+
+pub fn chunks_dirty(tilemap: Res<Tilemap>) -> bool {
+    tilemap.is_dirty()
+}
@@ -0,0 +1,13 @@
+use bevy::prelude::*;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TreeKind {
+    Fall,
+    Fruit,
+    Moss,
+}
+
+#[derive(Component, Debug)]
+pub struct Tree {
+    pub remaining_resources: usize,
+}
@@ -0,0 +1,22 @@
+mod components;
+mod systems;
+
+use crate::{stone::spawn_stone_tiles, GameState};
+use bevy::prelude::*;
+
+pub use components::*;
+
+pub struct TreePlugin;
+
+impl Plugin for TreePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::WorldSpawn),
+            systems::spawn_trees.after(spawn_stone_tiles),
+        )
+        .add_systems(
+            Update,
+            (systems::update_tree_sprite).run_if(in_state(GameState::Main)),
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use super::{Tree, TreeKind};
+use crate::{
+    assets::trees::{TreeAsset, TreeCollection},
+    navmesh::components::Navmesh,
+    rng::GameRng,
+    tilemap::Tilemap,
+    worldgen::{place_trees, WorldGenPreset},
+    WorldNoise, TILE_SIZE,
+};
+use bevy::prelude::*;
+
+pub const MAX_WOOD_PER_TREE: usize = 300;
+
+fn tree_kind_to_resource(tree_kind: TreeKind, tree_collection: &TreeCollection) -> &dyn TreeAsset {
+    match tree_kind {
+        TreeKind::Fall => &tree_collection.fall_tree,
+        TreeKind::Fruit => &tree_collection.fruit_tree,
+        TreeKind::Moss => &tree_collection.moss_tree,
+    }
+}
+
+pub fn spawn_trees(
+    mut commands: Commands,
+    tree_collection: Res<TreeCollection>,
+    world_noise: Res<WorldNoise>,
+    tilemap: Res<Tilemap>,
+    mut navmesh: ResMut<Navmesh>,
+    mut game_rng: ResMut<GameRng>,
+    preset: Res<WorldGenPreset>,
+) {
+    // stones are spawned first, so anything not walkable already has a stone on it
+    let trees = place_trees(
+        &world_noise,
+        tilemap.tiles(),
+        |pos| navmesh.get(pos).is_some_and(|tile| !tile.walkable),
+        &preset,
+        &mut game_rng.trees,
+    );
+
+    for (pos, tree_kind) in trees.iter() {
+        let Some(tree_kind) = *tree_kind else {
+            continue;
+        };
+
+        let tree_entity = commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+                        anchor: bevy::sprite::Anchor::BottomLeft,
+                        ..default()
+                    },
+                    texture: tree_kind_to_resource(tree_kind, &tree_collection).get_large(),
+                    transform: Transform::from_translation(pos.corner().extend(0.5)),
+                    ..default()
+                },
+                tree_kind,
+                Tree {
+                    remaining_resources: MAX_WOOD_PER_TREE,
+                },
+            ))
+            .id();
+
+        navmesh.set_walkable(pos, false);
+        navmesh.occupy(pos, tree_entity);
+    }
+}
+
+pub fn update_tree_sprite(
+    mut q_tree: Query<(&Tree, &TreeKind, &mut Handle<Image>, &mut Sprite), Changed<Tree>>,
+    tree_collection: Res<TreeCollection>,
+) {
+    for (tree, kind, mut image, mut sprite) in &mut q_tree {
+        let tree_resource = tree_kind_to_resource(*kind, &tree_collection);
+        let tree_image = if tree.remaining_resources < MAX_WOOD_PER_TREE / 3 {
+            sprite.custom_size = Some(Vec2::new(TILE_SIZE * 0.6, TILE_SIZE * 0.6));
+            tree_resource.get_small()
+        } else if tree.remaining_resources < MAX_WOOD_PER_TREE * 2 / 3 {
+            sprite.custom_size = Some(Vec2::new(TILE_SIZE * 0.8, TILE_SIZE * 0.8));
+            tree_resource.get_medium()
+        } else {
+            sprite.custom_size = Some(Vec2::new(TILE_SIZE, TILE_SIZE));
+            tree_resource.get_large()
+        };
+
+        *image = tree_image;
+    }
+}
@@ -1,5 +1,9 @@
 use super::styles::*;
-use crate::{pawn::SpawnPawnRequestEvent, GameResources, GameState};
+use crate::{
+    economy::{ResourceKind, ResourceLedger, PAWN_COST},
+    pawn::SpawnPawnRequestEvent,
+    GameResources, GameState,
+};
 use bevy::prelude::*;
 use bevy_ui_dsl::*;
 
@@ -11,9 +15,14 @@ impl Plugin for GameStateUIPlugin {
             .add_systems(OnExit(GameState::Main), destroy_game_state_ui)
             .add_systems(
                 Update,
-                ((update_resource_counter, update_pawn_counter).run_if(
-                    in_state(GameState::Main).and_then(resource_changed::<GameResources>()),
-                ),),
+                (
+                    update_resource_counters.run_if(
+                        in_state(GameState::Main).and_then(resource_changed::<ResourceLedger>()),
+                    ),
+                    update_pawn_counter.run_if(
+                        in_state(GameState::Main).and_then(resource_changed::<GameResources>()),
+                    ),
+                ),
             )
             .add_systems(
                 Update,
@@ -28,7 +37,7 @@ impl Plugin for GameStateUIPlugin {
 }
 
 #[derive(Component)]
-struct GameResourceCounter;
+struct ResourceCounter(ResourceKind);
 #[derive(Component)]
 struct PawnResourceCounter;
 
@@ -44,7 +53,6 @@ struct WallSpawnButton;
 struct TurretSpawnButton;
 
 fn game_state_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let mut resource_entity = None;
     let mut pawn_entity = None;
 
     let mut pawn_spawn_button = None;
@@ -57,10 +65,23 @@ fn game_state_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
         &mut commands,
         |p| {
             node(top_right_anchor, p, |p| {
-                node((), p, |p| {
-                    text("Resources: ", c_pixel_text, text_style(Some(28.)), p);
-                    text("0", c_pixel_text, text_style(Some(28.)), p).set(&mut resource_entity);
-                });
+                for kind in ResourceKind::ALL {
+                    node((), p, |p| {
+                        text(
+                            format!("{}: ", kind.name()),
+                            c_pixel_text,
+                            text_style(Some(28.)),
+                            p,
+                        );
+                        texti(
+                            "0",
+                            c_pixel_text,
+                            text_style(Some(28.)),
+                            ResourceCounter(kind),
+                            p,
+                        );
+                    });
+                }
                 node((), p, |p| {
                     text("Pawns: ", c_pixel_text, text_style(Some(28.)), p);
                     text("0", c_pixel_text, text_style(Some(28.)), p).set(&mut pawn_entity);
@@ -68,20 +89,27 @@ fn game_state_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
             });
             node(bottom_center_anchor, p, |p| {
                 // pawn spawn button
-                button(spawn_menu_button(Some("objects/pawns/pawn.png")), p, |_| {})
-                    .set(&mut pawn_spawn_button);
-                // wall spawn button
-                button(spawn_menu_button(None), p, |p| {
-                    text("Wall", (), (), p);
-                })
-                .set(&mut wall_spawn_button);
-                // turret spawn button
-                button(
-                    spawn_menu_button(Some("objects/turret/machineGun.png")),
-                    p,
-                    |_| {},
-                )
-                .set(&mut turret_spawn_button);
+                node(spawn_menu_item, p, |p| {
+                    button(spawn_menu_button(Some("objects/pawns/pawn.png")), p, |_| {})
+                        .set(&mut pawn_spawn_button);
+                    text(PAWN_COST.to_string(), (), text_style(Some(12.)), p);
+                });
+                // wall spawn button, which gets a price once walls can be built
+                node(spawn_menu_item, p, |p| {
+                    button(spawn_menu_button(None), p, |p| {
+                        text("Wall", (), (), p);
+                    })
+                    .set(&mut wall_spawn_button);
+                });
+                // turret spawn button, which gets a price once turrets can be built
+                node(spawn_menu_item, p, |p| {
+                    button(
+                        spawn_menu_button(Some("objects/turret/machineGun.png")),
+                        p,
+                        |_| {},
+                    )
+                    .set(&mut turret_spawn_button);
+                });
             });
         },
     );
@@ -99,9 +127,6 @@ fn game_state_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
         .entity(turret_spawn_button.unwrap())
         .insert(TurretSpawnButton);
 
-    commands
-        .entity(resource_entity.unwrap())
-        .insert(GameResourceCounter);
     commands.entity(root_entity).insert(GameStateUI);
 }
 
@@ -111,12 +136,12 @@ fn destroy_game_state_ui(mut commands: Commands, query: Query<Entity, With<GameS
     }
 }
 
-fn update_resource_counter(
-    game_resources: Res<GameResources>,
-    mut query: Query<&mut Text, With<GameResourceCounter>>,
+fn update_resource_counters(
+    ledger: Res<ResourceLedger>,
+    mut query: Query<(&mut Text, &ResourceCounter)>,
 ) {
-    for mut text in &mut query {
-        text.sections[0].value = game_resources.stone.to_string();
+    for (mut text, ResourceCounter(kind)) in &mut query {
+        text.sections[0].value = ledger.get(*kind).to_string();
     }
 }
 
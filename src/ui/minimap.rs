@@ -0,0 +1,246 @@
+use super::styles::*;
+use crate::{
+    factory::{Factory, Placed, FACTORY_SIZE},
+    fog::{FogOfWar, TileVisibility},
+    pawn::components::{Enemy, Pawn},
+    stone::Stone,
+    tilemap::{TileType, Tilemap},
+    tree::Tree,
+    utils::*,
+    CameraMetadata, GameState, MapSettings,
+};
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    ui::RelativeCursorPosition,
+};
+use bevy_ui_dsl::*;
+
+/// The width and height of the minimap on screen
+const MINIMAP_SIZE: f32 = 192.;
+/// How often the minimap is redrawn, in seconds
+const MINIMAP_REFRESH: f32 = 0.25;
+
+const WATER_COLOR: [u8; 3] = [40, 90, 200];
+const DIRT_COLOR: [u8; 3] = [120, 85, 50];
+const GRASS_COLOR: [u8; 3] = [70, 150, 60];
+const STONE_COLOR: [u8; 3] = [150, 150, 150];
+const TREE_COLOR: [u8; 3] = [20, 85, 30];
+const FACTORY_COLOR: [u8; 3] = [240, 200, 40];
+const PAWN_COLOR: [u8; 3] = [80, 220, 255];
+const ENEMY_COLOR: [u8; 3] = [230, 40, 40];
+const VIEWPORT_COLOR: [u8; 3] = [255, 255, 255];
+
+pub struct MinimapUIPlugin;
+
+impl Plugin for MinimapUIPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Main), create_minimap)
+            .add_systems(OnExit(GameState::Main), destroy_minimap)
+            .add_systems(
+                Update,
+                (listen_for_minimap_click, draw_minimap).run_if(in_state(GameState::Main)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct MinimapUI;
+
+/// The image node of the minimap, which can be clicked to move the camera
+#[derive(Component)]
+struct MinimapImage;
+
+#[derive(Resource)]
+struct Minimap {
+    image: Handle<Image>,
+    refresh_timer: Timer,
+}
+
+fn minimap_anchor(node: &mut NodeBundle) {
+    node.style = Style {
+        position_type: PositionType::Absolute,
+        left: Val::Px(5.0),
+        bottom: Val::Px(5.0),
+        border: UiRect::all(Val::Px(2.0)),
+        ..default()
+    };
+    node.border_color = BorderColor(MENU_BUTTON_BORDER);
+}
+
+fn create_minimap(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    map_settings: Res<MapSettings>,
+) {
+    let image = images.add(Image::new_fill(
+        Extent3d {
+            width: map_settings.size as u32,
+            height: map_settings.size as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+
+    let minimap_image = image.clone();
+    let root_entity = root(minimap_anchor, &asset_server, &mut commands, |p| {
+        imagei(
+            move |_: &AssetServer, b: &mut ImageBundle| {
+                b.image = UiImage::new(minimap_image.clone());
+                b.style.width = Val::Px(MINIMAP_SIZE);
+                b.style.height = Val::Px(MINIMAP_SIZE);
+            },
+            (
+                MinimapImage,
+                Interaction::default(),
+                RelativeCursorPosition::default(),
+            ),
+            p,
+        );
+    });
+
+    commands
+        .entity(root_entity)
+        .insert((MinimapUI, Name::new("Minimap")));
+
+    let mut refresh_timer = Timer::from_seconds(MINIMAP_REFRESH, TimerMode::Repeating);
+    // draw straight away instead of showing a black square for the first refresh
+    refresh_timer.tick(refresh_timer.duration());
+
+    commands.insert_resource(Minimap {
+        image,
+        refresh_timer,
+    });
+}
+
+fn destroy_minimap(mut commands: Commands, query: Query<Entity, With<MinimapUI>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<Minimap>();
+}
+
+fn listen_for_minimap_click(
+    q_minimap: Query<(&Interaction, &RelativeCursorPosition), With<MinimapImage>>,
+    mut q_camera: Query<&mut CameraMetadata>,
+    map_settings: Res<MapSettings>,
+) {
+    let Ok(mut camera) = q_camera.get_single_mut() else {
+        return;
+    };
+
+    for (interaction, cursor) in &q_minimap {
+        let (Interaction::Pressed, Some(normalized)) = (interaction, cursor.normalized) else {
+            continue;
+        };
+
+        // the ui measures from the top left, the world from the bottom left
+        let target = Vec2::new(normalized.x, 1. - normalized.y) * map_settings.world_size();
+        camera.target = target.extend(camera.target.z);
+    }
+}
+
+fn set_pixel(image: &mut Image, size: usize, pos: TilePos, color: [u8; 3]) {
+    if pos.x() < 0 || pos.y() < 0 || pos.x() as usize >= size || pos.y() as usize >= size {
+        return;
+    }
+
+    let row = size - 1 - pos.y() as usize;
+    let index = (row * size + pos.x() as usize) * 4;
+
+    if let Some(pixel) = image.data.get_mut(index..index + 3) {
+        pixel.copy_from_slice(&color);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_minimap(
+    mut minimap: ResMut<Minimap>,
+    mut images: ResMut<Assets<Image>>,
+    time: Res<Time>,
+    tilemap: Res<Tilemap>,
+    fog: Res<FogOfWar>,
+    q_stones: Query<&Transform, With<Stone>>,
+    q_trees: Query<&Transform, With<Tree>>,
+    q_factory: Query<&Transform, (With<Factory>, With<Placed>)>,
+    q_pawns: Query<(&Transform, Has<Enemy>), With<Pawn>>,
+    q_camera: Query<(&GlobalTransform, &OrthographicProjection), With<CameraMetadata>>,
+) {
+    if !minimap.refresh_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let Some(image) = images.get_mut(&minimap.image) else {
+        return;
+    };
+
+    let size = tilemap.tiles().width();
+    let visibility = |pos: TilePos| fog.tiles.get(pos).copied().unwrap_or_default();
+
+    // the ground, darkened where the fog of war is
+    for (pos, tile_type) in tilemap.tiles().iter() {
+        let color = match tile_type {
+            TileType::Water => WATER_COLOR,
+            TileType::Dirt => DIRT_COLOR,
+            TileType::Grass => GRASS_COLOR,
+        };
+
+        let color = match visibility(pos) {
+            TileVisibility::Unexplored => [0, 0, 0],
+            TileVisibility::Explored => color.map(|channel| channel / 2),
+            TileVisibility::Visible => color,
+        };
+
+        set_pixel(image, size, pos, color);
+    }
+
+    let resources = q_stones
+        .iter()
+        .map(|transform| (transform, STONE_COLOR))
+        .chain(q_trees.iter().map(|transform| (transform, TREE_COLOR)));
+
+    for (transform, color) in resources {
+        let pos = transform.tile_pos();
+        if visibility(pos) != TileVisibility::Unexplored {
+            set_pixel(image, size, pos, color);
+        }
+    }
+
+    for transform in &q_factory {
+        let pos = transform.tile_pos();
+        let footprint = IVec2::splat(FACTORY_SIZE as i32 - 1);
+        for pos in tilemap.tiles().rect(pos, pos + footprint) {
+            set_pixel(image, size, pos, FACTORY_COLOR);
+        }
+    }
+
+    for (transform, is_enemy) in &q_pawns {
+        let pos = transform.tile_pos();
+
+        // enemies only show up where the colony can see them, the same as in the world
+        if is_enemy && fog.is_visible(pos) {
+            set_pixel(image, size, pos, ENEMY_COLOR);
+        } else if !is_enemy {
+            set_pixel(image, size, pos, PAWN_COLOR);
+        }
+    }
+
+    // outline the part of the world the camera is looking at
+    for (transform, projection) in &q_camera {
+        let center = transform.translation().truncate();
+        let min = WorldPos(center + projection.area.min).tile();
+        let max = WorldPos(center + projection.area.max).tile();
+
+        for x in min.x()..=max.x() {
+            set_pixel(image, size, TilePos::new(x, min.y()), VIEWPORT_COLOR);
+            set_pixel(image, size, TilePos::new(x, max.y()), VIEWPORT_COLOR);
+        }
+        for y in min.y()..=max.y() {
+            set_pixel(image, size, TilePos::new(min.x(), y), VIEWPORT_COLOR);
+            set_pixel(image, size, TilePos::new(max.x(), y), VIEWPORT_COLOR);
+        }
+    }
+}
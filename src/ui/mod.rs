@@ -1,5 +1,7 @@
 mod factory_state;
 mod game_state;
+mod minimap;
+mod new_game_state;
 mod styles;
 
 use bevy::prelude::*;
@@ -11,6 +13,8 @@ impl Plugin for UIPlugin {
         app.add_plugins((
             factory_state::FactoryStateUIPlugin,
             game_state::GameStateUIPlugin,
+            minimap::MinimapUIPlugin,
+            new_game_state::NewGameStateUIPlugin,
         ));
     }
 }
@@ -0,0 +1,321 @@
+use super::styles::*;
+use crate::{
+    assets::WorldGenPresets,
+    rng::{parse_seed, GameRng},
+    worldgen::WorldGenPreset,
+    GameState, MapSettings,
+};
+use bevy::prelude::*;
+use bevy_ui_dsl::*;
+use rand::random;
+
+const MAX_SEED_LENGTH: usize = 32;
+
+pub struct NewGameStateUIPlugin;
+
+impl Plugin for NewGameStateUIPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::NewGame), create_ui)
+            .add_systems(OnExit(GameState::NewGame), destroy_ui)
+            .add_systems(
+                Update,
+                (
+                    listen_for_map_size_select,
+                    highlight_selected_map_size,
+                    listen_for_preset_select,
+                    highlight_selected_preset,
+                    listen_for_seed_input,
+                    listen_for_random_seed,
+                    update_seed_text,
+                    listen_for_start,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::NewGame)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct NewGameUI;
+
+#[derive(Component)]
+struct MapSizeButton(usize);
+
+/// Selects the preset at this index of [`WorldGenPresets`]
+#[derive(Component)]
+struct PresetButton(usize);
+
+#[derive(Component)]
+struct StartButton;
+
+#[derive(Component)]
+struct RandomSeedButton;
+
+#[derive(Component)]
+struct SeedText;
+
+/// The seed as typed on the new game screen. Parsed into a [`GameRng`] when the game starts
+#[derive(Resource)]
+struct SeedEntry(String);
+
+/// The index of the preset picked on the new game screen
+#[derive(Resource)]
+struct PresetSelection(usize);
+
+fn create_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    presets: Res<WorldGenPresets>,
+    preset_assets: Res<Assets<WorldGenPreset>>,
+) {
+    let seed = random::<u64>().to_string();
+
+    // the world camera isn't spawned until the map is built, so the menu needs its own
+    commands.spawn((
+        Camera2dBundle::default(),
+        NewGameUI,
+        Name::new("NewGameCamera"),
+    ));
+
+    let root_entity = root(
+        root_full_screen(Some(JustifyContent::Center), Some(AlignItems::Center)),
+        &asset_server,
+        &mut commands,
+        |p| {
+            node(menu_panel, p, |p| {
+                text("New Game", (), text_style(Some(40.)), p);
+                text("Map size", (), text_style(Some(28.)), p);
+                node(menu_row, p, |p| {
+                    for size in MapSettings::SIZES {
+                        text_buttoni(
+                            format!("{size}x{size}"),
+                            menu_button,
+                            text_style(Some(24.)),
+                            MapSizeButton(size),
+                            p,
+                        );
+                    }
+                });
+                text("World", (), text_style(Some(28.)), p);
+                node(menu_row, p, |p| {
+                    for (index, handle) in presets.presets.iter().enumerate() {
+                        let Some(preset) = preset_assets.get(handle) else {
+                            continue;
+                        };
+
+                        text_buttoni(
+                            preset.name.clone(),
+                            menu_button,
+                            text_style(Some(24.)),
+                            PresetButton(index),
+                            p,
+                        );
+                    }
+                });
+                text("Seed (type or paste)", (), text_style(Some(28.)), p);
+                node(menu_row, p, |p| {
+                    texti(
+                        seed.clone(),
+                        c_pixel_text,
+                        text_style(Some(24.)),
+                        SeedText,
+                        p,
+                    );
+                    text_buttoni(
+                        "Random",
+                        menu_button,
+                        text_style(Some(24.)),
+                        RandomSeedButton,
+                        p,
+                    );
+                });
+                text_buttoni("Start", menu_button, text_style(Some(28.)), StartButton, p);
+            });
+        },
+    );
+
+    commands
+        .entity(root_entity)
+        .insert((NewGameUI, Name::new("NewGameUI")));
+    commands.insert_resource(SeedEntry(seed));
+    commands.insert_resource(PresetSelection(0));
+}
+
+fn destroy_ui(mut commands: Commands, query: Query<Entity, With<NewGameUI>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<SeedEntry>();
+    commands.remove_resource::<PresetSelection>();
+}
+
+fn listen_for_map_size_select(
+    q_buttons: Query<(&Interaction, &MapSizeButton), Changed<Interaction>>,
+    mut map_settings: ResMut<MapSettings>,
+) {
+    for (interaction, MapSizeButton(size)) in &q_buttons {
+        if let Interaction::Pressed = interaction {
+            map_settings.size = *size;
+        }
+    }
+}
+
+fn highlight_selected_map_size(
+    mut q_buttons: Query<(&MapSizeButton, &mut BorderColor)>,
+    map_settings: Res<MapSettings>,
+) {
+    for (MapSizeButton(size), mut border) in &mut q_buttons {
+        border.0 = if *size == map_settings.size {
+            MENU_BUTTON_SELECTED_BORDER
+        } else {
+            MENU_BUTTON_BORDER
+        };
+    }
+}
+
+fn listen_for_preset_select(
+    q_buttons: Query<(&Interaction, &PresetButton), Changed<Interaction>>,
+    mut selection: ResMut<PresetSelection>,
+) {
+    for (interaction, PresetButton(index)) in &q_buttons {
+        if let Interaction::Pressed = interaction {
+            selection.0 = *index;
+        }
+    }
+}
+
+fn highlight_selected_preset(
+    mut q_buttons: Query<(&PresetButton, &mut BorderColor)>,
+    selection: Res<PresetSelection>,
+) {
+    for (PresetButton(index), mut border) in &mut q_buttons {
+        border.0 = if *index == selection.0 {
+            MENU_BUTTON_SELECTED_BORDER
+        } else {
+            MENU_BUTTON_BORDER
+        };
+    }
+}
+
+fn listen_for_seed_input(
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut seed_entry: ResMut<SeedEntry>,
+) {
+    let seed = &mut seed_entry.0;
+
+    if keys.just_pressed(KeyCode::Back) {
+        seed.pop();
+    }
+
+    let ctrl_pressed = keys.any_pressed([
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::SuperLeft,
+        KeyCode::SuperRight,
+    ]);
+    if ctrl_pressed && keys.just_pressed(KeyCode::V) {
+        if let Some(pasted) = read_clipboard() {
+            *seed = pasted.trim().to_string();
+        }
+    }
+
+    for ReceivedCharacter { char, .. } in characters.read() {
+        if !char.is_control() && !ctrl_pressed {
+            seed.push(*char);
+        }
+    }
+
+    truncate_seed(seed);
+}
+
+/// Cuts `seed` down to [`MAX_SEED_LENGTH`] characters, which may be several bytes each
+fn truncate_seed(seed: &mut String) {
+    if let Some((end, _)) = seed.char_indices().nth(MAX_SEED_LENGTH) {
+        seed.truncate(end);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_clipboard() -> Option<String> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|err| warn!("Unable to read the clipboard: {err}"))
+        .ok()
+}
+
+/// The browser clipboard API is asynchronous and permission gated, so the web build only supports typing a seed
+#[cfg(target_arch = "wasm32")]
+fn read_clipboard() -> Option<String> {
+    None
+}
+
+fn listen_for_random_seed(
+    q_random: Query<&Interaction, (With<RandomSeedButton>, Changed<Interaction>)>,
+    mut seed_entry: ResMut<SeedEntry>,
+) {
+    for interaction in &q_random {
+        if let Interaction::Pressed = interaction {
+            seed_entry.0 = random::<u64>().to_string();
+        }
+    }
+}
+
+fn update_seed_text(seed_entry: Res<SeedEntry>, mut q_seed_text: Query<&mut Text, With<SeedText>>) {
+    if !seed_entry.is_changed() {
+        return;
+    }
+
+    for mut text in &mut q_seed_text {
+        text.sections[0].value = format!("{}_", seed_entry.0);
+    }
+}
+
+fn listen_for_start(
+    mut commands: Commands,
+    q_start: Query<&Interaction, (With<StartButton>, Changed<Interaction>)>,
+    seed_entry: Res<SeedEntry>,
+    selection: Res<PresetSelection>,
+    presets: Res<WorldGenPresets>,
+    preset_assets: Res<Assets<WorldGenPreset>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for interaction in &q_start {
+        if let Interaction::Pressed = interaction {
+            let game_rng = parse_seed(&seed_entry.0)
+                .map(GameRng::from_seed)
+                .unwrap_or_default();
+
+            let preset = presets
+                .presets
+                .get(selection.0)
+                .and_then(|handle| preset_assets.get(handle))
+                .cloned()
+                .unwrap_or_default();
+
+            commands.insert_resource(game_rng);
+            commands.insert_resource(preset);
+            game_state.set(GameState::WorldSpawn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_seed_keeps_short_seeds() {
+        let mut seed = "hello".to_string();
+        truncate_seed(&mut seed);
+        assert_eq!(seed, "hello");
+    }
+
+    #[test]
+    fn truncate_seed_cuts_on_char_boundaries() {
+        let mut seed = "é日".repeat(20);
+        truncate_seed(&mut seed);
+        assert_eq!(seed.chars().count(), MAX_SEED_LENGTH);
+        assert_eq!(seed, "é日".repeat(MAX_SEED_LENGTH / 2));
+    }
+}
@@ -68,6 +68,17 @@ pub fn bottom_center_anchor(node: &mut NodeBundle) {
     node.style.justify_self = JustifySelf::End;
 }
 
+/// A spawn menu button with its price underneath
+pub fn spawn_menu_item(node: &mut NodeBundle) {
+    node.style = Style {
+        display: Display::Flex,
+        flex_direction: FlexDirection::Column,
+        align_items: AlignItems::Center,
+        margin: UiRect::horizontal(Val::Px(5.0)),
+        ..default()
+    };
+}
+
 pub fn spawn_menu_button(
     image_location: Option<&'static str>,
 ) -> impl Fn(&AssetServer, &mut ButtonBundle) {
@@ -87,3 +98,41 @@ pub fn spawn_menu_button(
         b.border_color = BorderColor(Color::WHITE);
     }
 }
+
+pub fn menu_panel(node: &mut NodeBundle) {
+    node.style = Style {
+        display: Display::Flex,
+        flex_direction: FlexDirection::Column,
+        align_items: AlignItems::Center,
+        padding: UiRect::all(Val::Px(20.0)),
+        row_gap: Val::Px(10.0),
+        ..default()
+    };
+    node.background_color = BackgroundColor(Color::rgba(0., 0., 0., 0.85));
+}
+
+pub fn menu_row(node: &mut NodeBundle) {
+    node.style = Style {
+        display: Display::Flex,
+        flex_direction: FlexDirection::Row,
+        align_items: AlignItems::Center,
+        column_gap: Val::Px(10.0),
+        ..default()
+    };
+}
+
+pub fn menu_button(_: &AssetServer, b: &mut ButtonBundle) {
+    b.style = Style {
+        display: Display::Flex,
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        padding: UiRect::all(Val::Px(10.0)),
+        border: UiRect::all(Val::Px(2.0)),
+        ..default()
+    };
+    b.border_color = BorderColor(MENU_BUTTON_BORDER);
+    b.background_color = BackgroundColor(Color::rgba(0., 0., 0., 0.85));
+}
+
+pub const MENU_BUTTON_BORDER: Color = Color::GRAY;
+pub const MENU_BUTTON_SELECTED_BORDER: Color = Color::WHITE;
@@ -1,66 +1,96 @@
 use crate::TILE_SIZE;
 use bevy::prelude::*;
+use std::ops::{Add, Sub};
 
-pub trait TranslationHelper {
-    fn world_pos_to_tile(&self) -> Vec2;
-    fn tile_pos_to_world(&self) -> Vec2;
+/// The position of a tile within a [`crate::grid::Grid`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub struct TilePos(pub IVec2);
+
+impl TilePos {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self(IVec2::new(x, y))
+    }
+
+    pub fn x(&self) -> i32 {
+        self.0.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.0.y
+    }
+
+    /// The bottom left corner of the tile in world space. Everything on the map is anchored to the bottom
+    /// left, so this is where a sprite covering the tile should be placed.
+    pub fn corner(self) -> WorldPos {
+        WorldPos(self.0.as_vec2() * TILE_SIZE)
+    }
+
+    /// The center of the tile in world space
+    pub fn center(self) -> WorldPos {
+        WorldPos(self.corner().0 + Vec2::splat(TILE_SIZE / 2.))
+    }
+
+    /// The straight line distance to `other`, measured in tiles
+    pub fn distance(self, other: TilePos) -> f32 {
+        self.0.as_vec2().distance(other.0.as_vec2())
+    }
 }
 
-impl TranslationHelper for Transform {
-    fn world_pos_to_tile(&self) -> Vec2 {
-        Vec2::new(
-            (self.translation.x / TILE_SIZE).floor(),
-            (self.translation.y / TILE_SIZE).floor(),
-        )
+impl Add<IVec2> for TilePos {
+    type Output = TilePos;
+
+    fn add(self, rhs: IVec2) -> Self::Output {
+        TilePos(self.0 + rhs)
     }
+}
+
+impl Sub<IVec2> for TilePos {
+    type Output = TilePos;
 
-    fn tile_pos_to_world(&self) -> Vec2 {
-        Vec2::new(
-            self.translation.x * TILE_SIZE - TILE_SIZE / 2.,
-            self.translation.y * TILE_SIZE - TILE_SIZE / 2.,
-        )
+    fn sub(self, rhs: IVec2) -> Self::Output {
+        TilePos(self.0 - rhs)
     }
 }
 
-impl TranslationHelper for GlobalTransform {
-    fn world_pos_to_tile(&self) -> Vec2 {
-        Vec2::new(
-            (self.translation().x / TILE_SIZE).floor(),
-            (self.translation().y / TILE_SIZE).floor(),
-        )
+/// A position in world space, measured in pixels
+#[derive(Debug, Default, Clone, Copy, PartialEq, Reflect)]
+pub struct WorldPos(pub Vec2);
+
+impl WorldPos {
+    /// The tile which contains this position
+    pub fn tile(self) -> TilePos {
+        TilePos((self.0 / TILE_SIZE).floor().as_ivec2())
     }
 
-    fn tile_pos_to_world(&self) -> Vec2 {
-        Vec2::new(
-            self.translation().x * TILE_SIZE - TILE_SIZE / 2.,
-            self.translation().y * TILE_SIZE - TILE_SIZE / 2.,
-        )
+    pub fn extend(self, z: f32) -> Vec3 {
+        self.0.extend(z)
     }
 }
 
-impl TranslationHelper for Vec3 {
-    fn world_pos_to_tile(&self) -> Vec2 {
-        Vec2::new((self.x / TILE_SIZE).floor(), (self.y / TILE_SIZE).floor())
+/// Anything with a location in the world which can be converted to a [`WorldPos`] or [`TilePos`]
+pub trait WorldPosition {
+    fn world_pos(&self) -> WorldPos;
+
+    fn tile_pos(&self) -> TilePos {
+        self.world_pos().tile()
     }
+}
 
-    fn tile_pos_to_world(&self) -> Vec2 {
-        Vec2::new(
-            self.x * TILE_SIZE - TILE_SIZE / 2.,
-            self.y * TILE_SIZE - TILE_SIZE / 2.,
-        )
+impl WorldPosition for Transform {
+    fn world_pos(&self) -> WorldPos {
+        WorldPos(self.translation.truncate())
     }
 }
 
-impl TranslationHelper for Vec2 {
-    fn world_pos_to_tile(&self) -> Vec2 {
-        Vec2::new((self.x / TILE_SIZE).floor(), (self.y / TILE_SIZE).floor())
+impl WorldPosition for GlobalTransform {
+    fn world_pos(&self) -> WorldPos {
+        WorldPos(self.translation().truncate())
     }
+}
 
-    fn tile_pos_to_world(&self) -> Vec2 {
-        Vec2::new(
-            self.x * TILE_SIZE + TILE_SIZE / 2.,
-            self.y * TILE_SIZE + TILE_SIZE / 2.,
-        )
+impl WorldPosition for Vec3 {
+    fn world_pos(&self) -> WorldPos {
+        WorldPos(self.truncate())
     }
 }
 
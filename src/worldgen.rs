@@ -0,0 +1,266 @@
+use crate::{
+    grid::Grid,
+    stone::{StoneDeposit, StoneKind},
+    tilemap::TileType,
+    tree::TreeKind,
+    utils::TilePos,
+    WorldNoise,
+};
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    reflect::TypePath,
+    utils::BoxedFuture,
+};
+use noisy_bevy::simplex_noise_2d_seeded;
+use rand::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+/// The chance of a tree growing on a tile inside a forest, so forests have gaps to walk through
+const TREE_DENSITY: f64 = 0.4;
+
+/// The numbers which shape a generated world. Presets live in `assets/presets/*.preset.ron`, and the one
+/// picked on the new game screen is inserted as a resource before the world is spawned.
+#[derive(Asset, Resource, TypePath, Deserialize, Debug, Clone)]
+pub struct WorldGenPreset {
+    pub name: String,
+    /// Everything below this value in the base world noise is water
+    pub water_cutoff: f32,
+    /// Everything at or above this value in the base world noise is grass, the rest is dirt
+    pub grass_cutoff: f32,
+    /// How many tiles one unit of the base world noise is spread across. Bigger values give bigger features
+    pub terrain_scale: f32,
+    /// The same as `terrain_scale`, for the noise trees grow from
+    pub resource_scale: f32,
+    /// Stones are spawned where the base world noise is above this value
+    pub stone_cutoff: f32,
+    /// The scale of the noise which decides the kind of each stone, bigger values give bigger patches of one kind
+    pub stone_kind_scale: f32,
+    /// Trees only grow where the resource noise is above this value
+    pub tree_cutoff: f32,
+}
+
+impl Default for WorldGenPreset {
+    fn default() -> Self {
+        Self {
+            name: "Open Plains".to_string(),
+            water_cutoff: -0.45,
+            grass_cutoff: 0.0,
+            terrain_scale: 75.,
+            resource_scale: 100.,
+            stone_cutoff: 0.70,
+            stone_kind_scale: 150.,
+            tree_cutoff: 0.3,
+        }
+    }
+}
+
+/// Fill both noise layers of `world_noise`, which must already be sized and seeded
+pub fn generate_noise(world_noise: &mut WorldNoise, preset: &WorldGenPreset) {
+    let WorldNoise { seed, offset, .. } = *world_noise;
+
+    for (pos, value) in world_noise.base_world.iter_mut() {
+        let perlin_location = (pos.0 + IVec2::splat(offset as i32)).as_vec2();
+        *value = simplex_noise_2d_seeded(perlin_location / preset.terrain_scale, seed);
+    }
+
+    for (pos, value) in world_noise.base_resources.iter_mut() {
+        let perlin_location = (pos.0 + IVec2::splat(offset as i32)).as_vec2();
+        *value = simplex_noise_2d_seeded(perlin_location / preset.resource_scale, seed);
+    }
+}
+
+/// Decide which kind of ground each tile is from the base world noise
+pub fn classify_tiles(base_world: &Grid<f32>, preset: &WorldGenPreset) -> Grid<TileType> {
+    let is_water = |pos: TilePos| {
+        base_world
+            .get(pos)
+            .is_some_and(|value| *value < preset.water_cutoff)
+    };
+
+    Grid::from_fn(base_world.width(), base_world.height(), |pos| {
+        let value = base_world.get(pos).copied().unwrap_or_default();
+
+        if value < preset.water_cutoff {
+            return TileType::Water;
+        }
+
+        // grass never touches water directly, so the shoreline is always a strip of dirt
+        let on_shore = base_world.neighbors_8(pos).any(is_water);
+
+        if value >= preset.grass_cutoff && !on_shore {
+            TileType::Grass
+        } else {
+            TileType::Dirt
+        }
+    })
+}
+
+fn get_neighbor_stone_kind(grid: &Grid<Option<StoneDeposit>>, pos: TilePos) -> Option<StoneKind> {
+    // check the direct neighbors first, then the diagonals
+    grid.neighbors_8(pos)
+        .find_map(|neighbor| grid.get(neighbor).copied().flatten())
+        .map(|deposit| deposit.kind)
+}
+
+/// Decide which tiles hold a stone, of which kind and how much it's worth. Stones come from two places: rock
+/// outcrops on the highest ground of the base world noise, and veins of each [`StoneKind`] which grow from
+/// the richest parts of the resource noise.
+pub fn classify_stones(
+    world_noise: &WorldNoise,
+    tiles: &Grid<TileType>,
+    preset: &WorldGenPreset,
+    rng: &mut impl Rng,
+) -> Grid<Option<StoneDeposit>> {
+    let size = world_noise.size();
+    let mut stones: Grid<Option<StoneDeposit>> = Grid::new(size, size);
+
+    for pos in stones.positions() {
+        let noise_value = world_noise.base_world.get(pos).copied().unwrap_or_default();
+
+        if noise_value <= preset.stone_cutoff {
+            continue;
+        }
+
+        let perlin_location = (pos.0 + IVec2::splat(world_noise.offset as i32)).as_vec2();
+
+        let kind_value =
+            simplex_noise_2d_seeded(perlin_location / preset.stone_kind_scale, world_noise.seed);
+
+        let stone_kind = if kind_value < -0.5 {
+            StoneKind::Capped
+        } else if (-0.5..-0.25).contains(&kind_value) {
+            StoneKind::Red
+        } else if (-0.25..0.).contains(&kind_value) {
+            StoneKind::Salt
+        } else if (0. ..0.25).contains(&kind_value) {
+            StoneKind::Stone
+        } else {
+            StoneKind::Tan
+        };
+
+        // stones next to each other are always the same kind, so deposits don't look patchy
+        let kind = get_neighbor_stone_kind(&stones, pos).unwrap_or(stone_kind);
+        let amount = rng.gen_range(kind.vein_settings().richness);
+
+        if let Some(tile) = stones.get_mut(pos) {
+            *tile = Some(StoneDeposit { kind, amount });
+        }
+    }
+
+    for (pos, resource_value) in world_noise.base_resources.iter() {
+        let vein_kind = StoneKind::BY_RARITY.into_iter().find(|kind| {
+            let settings = kind.vein_settings();
+            *resource_value >= settings.min_resource_noise && rng.gen_bool(settings.rarity)
+        });
+
+        if let Some(kind) = vein_kind {
+            grow_vein(&mut stones, tiles, pos, kind, rng);
+        }
+    }
+
+    stones
+}
+
+/// Grow a vein of `kind` from `start` by wandering in a random direction one tile at a time. Veins never
+/// grow into water or over other stones.
+fn grow_vein(
+    stones: &mut Grid<Option<StoneDeposit>>,
+    tiles: &Grid<TileType>,
+    start: TilePos,
+    kind: StoneKind,
+    rng: &mut impl Rng,
+) {
+    let settings = kind.vein_settings();
+    let mut pos = start;
+
+    for _ in 0..rng.gen_range(settings.vein_size) {
+        let is_ground = tiles.get(pos).is_some_and(|tile| *tile != TileType::Water);
+
+        if let Some(tile) = stones
+            .get_mut(pos)
+            .filter(|tile| is_ground && tile.is_none())
+        {
+            *tile = Some(StoneDeposit {
+                kind,
+                amount: rng.gen_range(settings.richness.clone()),
+            });
+        }
+
+        let Some(next) = stones.neighbors_8(pos).choose(rng) else {
+            break;
+        };
+        pos = next;
+    }
+}
+
+/// Decide which tiles grow a tree. Trees only grow on grass which isn't `blocked` by something else, like a
+/// stone
+pub fn place_trees(
+    world_noise: &WorldNoise,
+    tiles: &Grid<TileType>,
+    blocked: impl Fn(TilePos) -> bool,
+    preset: &WorldGenPreset,
+    rng: &mut impl Rng,
+) -> Grid<Option<TreeKind>> {
+    Grid::from_fn(tiles.width(), tiles.height(), |pos| {
+        let resource_value = world_noise.base_resources.get(pos).copied()?;
+
+        if resource_value < preset.tree_cutoff
+            || tiles.get(pos) != Some(&TileType::Grass)
+            || blocked(pos)
+            || !rng.gen_bool(TREE_DENSITY)
+        {
+            return None;
+        }
+
+        [TreeKind::Fall, TreeKind::Fruit, TreeKind::Moss]
+            .choose(rng)
+            .copied()
+    })
+}
+
+#[derive(Default)]
+pub struct WorldGenPresetLoader;
+
+#[derive(Debug, Error)]
+pub enum WorldGenPresetLoaderError {
+    #[error("Could not read the preset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse the preset: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for WorldGenPresetLoader {
+    type Asset = WorldGenPreset;
+    type Settings = ();
+    type Error = WorldGenPresetLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["preset.ron"]
+    }
+}
+
+pub struct WorldGenPlugin;
+
+impl Plugin for WorldGenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<WorldGenPreset>()
+            .init_asset_loader::<WorldGenPresetLoader>()
+            .init_resource::<WorldGenPreset>();
+    }
+}